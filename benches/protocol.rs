@@ -0,0 +1,69 @@
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use movebeam::{
+    msg::{Encoding, Message, Response, TimerInfo, TimerStatus},
+    socket::{decode_frame, encode_frame},
+};
+use std::time::Duration;
+
+/// Response to the `get` query of a bar module, the most frequent message
+fn timer_response() -> Vec<u8> {
+    Response::Timer(TimerInfo {
+        elapsed: Duration::from_secs(1234),
+        interval: Duration::from_secs(3000),
+        overdue: false,
+        status: TimerStatus::Running,
+        deferred: None,
+        blink: true,
+        fired_today: 3,
+        elapsed_today: Duration::from_secs(18600),
+    })
+    .encode()
+    .unwrap()
+}
+
+fn encoding(c: &mut Criterion) {
+    let msg = Message::Get("move".to_string());
+    c.bench_function("encode/allocating", |b| {
+        b.iter(|| black_box(&msg).encode().unwrap())
+    });
+    let mut buf = Vec::new();
+    c.bench_function("encode/reused", |b| {
+        b.iter(|| {
+            buf.clear();
+            black_box(&msg).encode_into(&mut buf).unwrap();
+        })
+    });
+    let resp = timer_response();
+    c.bench_function("decode/timer", |b| {
+        b.iter(|| Response::decode(black_box(&resp)).unwrap())
+    });
+}
+
+fn framing(c: &mut Criterion) {
+    let resp = timer_response();
+    // How frames were built before, with an encoded string and a concatenation per message
+    c.bench_function("frame/allocating", |b| {
+        b.iter(|| {
+            let encoded = STANDARD_NO_PAD.encode(black_box(&resp));
+            [encoded.as_bytes(), &[4]].concat()
+        })
+    });
+    let mut buf = Vec::new();
+    c.bench_function("frame/reused", |b| {
+        b.iter(|| {
+            buf.clear();
+            encode_frame(black_box(&resp), &mut buf);
+        })
+    });
+    let mut frame = Vec::new();
+    encode_frame(&resp, &mut frame);
+    frame.pop();
+    let mut decoded = Vec::new();
+    c.bench_function("unframe/reused", |b| {
+        b.iter(|| decode_frame(black_box(&frame), &mut decoded).unwrap())
+    });
+}
+
+criterion_group!(benches, encoding, framing);
+criterion_main!(benches);
@@ -0,0 +1,483 @@
+use crate::{
+    config::ActivitySource,
+    msg::{ActivityInfo, ActivityQuery, Encoding, Privacy},
+    socket::SocketClient,
+};
+use anyhow::{anyhow, bail, Context, Result};
+#[cfg(feature = "evdev")]
+use crossbeam_channel::Receiver;
+use parking_lot::Mutex;
+#[cfg(feature = "evdev")]
+use std::collections::VecDeque;
+use std::{
+    process::{Command, Stdio},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+#[cfg(feature = "x11")]
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    protocol::screensaver,
+    rust_connection::RustConnection,
+};
+
+/// Kind of a single input event
+pub enum InputEvent {
+    Keyboard,
+    Mouse,
+    Gamepad,
+}
+
+/// Interval at which the sources that run a process for each query are polled
+const PROCESS_POLL: Duration = Duration::from_secs(5);
+/// Interval at which the audio playback is checked
+const AUDIO_POLL: Duration = Duration::from_secs(10);
+
+/// Activity as reported by an activity source
+#[derive(Clone, Copy)]
+pub struct Activity {
+    pub input_elapsed: Duration,
+    /// Keyboard events per minute, if the source measures it
+    pub intensity: Option<f64>,
+}
+
+pub trait ActivityBackend: Send {
+    fn query(&mut self) -> Result<Activity>;
+}
+
+/// Connects to the first available source in order of preference
+///
+/// The input intensity is only requested from sources that need permission for it when `intensity` is set.
+pub fn connect(
+    sources: &[ActivitySource],
+    intensity: bool,
+) -> Result<(ActivitySource, Box<dyn ActivityBackend>)> {
+    for source in sources {
+        match connect_source(*source, intensity) {
+            Ok(backend) => {
+                info!("Using activity source {source}");
+                return Ok((*source, backend));
+            }
+            Err(e) => warn!("Activity source {source} unavailable: {e:#}"),
+        }
+    }
+    bail!("No activity source available")
+}
+
+fn connect_source(source: ActivitySource, intensity: bool) -> Result<Box<dyn ActivityBackend>> {
+    Ok(match source {
+        ActivitySource::Evdev => Box::new(Evdev {
+            client: SocketClient::connect(crate::activity_daemon_socket())?,
+            detail: if intensity {
+                Privacy::Counts
+            } else {
+                Privacy::Timestamps
+            },
+        }),
+        #[cfg(feature = "x11")]
+        ActivitySource::X11 => Box::new(X11::connect()?),
+        #[cfg(not(feature = "x11"))]
+        ActivitySource::X11 => bail!("built without the x11 feature"),
+        ActivitySource::Logind => Box::new(Polled::start(PROCESS_POLL, logind()?, logind)),
+        ActivitySource::Gnome => {
+            let gnome = DBusIdle {
+                connection: session_bus()?,
+                dest: "org.gnome.Mutter.IdleMonitor",
+                path: "/org/gnome/Mutter/IdleMonitor/Core",
+                interface: "org.gnome.Mutter.IdleMonitor",
+                method: "GetIdletime",
+            };
+            Box::new(Polled::start(PROCESS_POLL, gnome.query()?, move || {
+                gnome.query()
+            }))
+        }
+        // The screen locker of KDE answers with the idle time of KIdleTime
+        ActivitySource::Kde => {
+            let kde = DBusIdle {
+                connection: session_bus()?,
+                dest: "org.freedesktop.ScreenSaver",
+                path: "/org/freedesktop/ScreenSaver",
+                interface: "org.freedesktop.ScreenSaver",
+                method: "GetSessionIdleTime",
+            };
+            Box::new(Polled::start(PROCESS_POLL, kde.query()?, move || {
+                kde.query()
+            }))
+        }
+        // Skipped with a warning like an unavailable source, so the next one is used
+        ActivitySource::Wayland => bail!("not implemented yet"),
+    })
+}
+
+/// Window over which the input intensity of the in-process listener is measured
+#[cfg(feature = "evdev")]
+const INTENSITY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Starts listening to the input devices in this process instead of through the activity daemon
+///
+/// This needs read access to `/dev/input`, e.g. by adding the user to the `input` group.
+#[cfg(feature = "evdev")]
+pub fn listen() -> Result<Box<dyn ActivityBackend>> {
+    let devices = crate::input_listener::open_devices();
+    if devices.is_empty() {
+        bail!("No input devices accessible, add the user to the input group");
+    }
+    let (event_tx, events) = crossbeam_channel::unbounded();
+    thread::spawn(move || crate::input_listener::start_listener(devices, event_tx));
+    info!("Using the built-in input listener");
+    Ok(Box::new(Listener {
+        events,
+        last_input: Instant::now(),
+        keyboard_events: VecDeque::new(),
+    }))
+}
+
+#[cfg(not(feature = "evdev"))]
+pub fn listen() -> Result<Box<dyn ActivityBackend>> {
+    bail!("moved was built without the evdev feature, which the input listener needs")
+}
+
+/// Input events from the built-in listener
+#[cfg(feature = "evdev")]
+struct Listener {
+    events: Receiver<InputEvent>,
+    last_input: Instant,
+    keyboard_events: VecDeque<Instant>,
+}
+
+#[cfg(feature = "evdev")]
+impl ActivityBackend for Listener {
+    fn query(&mut self) -> Result<Activity> {
+        for event in self.events.try_iter() {
+            self.last_input = Instant::now();
+            if let InputEvent::Keyboard = event {
+                self.keyboard_events.push_back(Instant::now());
+            }
+        }
+        while self
+            .keyboard_events
+            .front()
+            .is_some_and(|t| t.elapsed() > INTENSITY_WINDOW)
+        {
+            self.keyboard_events.pop_front();
+        }
+        Ok(Activity {
+            input_elapsed: self.last_input.elapsed(),
+            intensity: Some(
+                self.keyboard_events.len() as f64 * 60.0 / INTENSITY_WINDOW.as_secs_f64(),
+            ),
+        })
+    }
+}
+
+/// Input events from the activity daemon
+struct Evdev {
+    client: SocketClient,
+    detail: Privacy,
+}
+
+impl ActivityBackend for Evdev {
+    fn query(&mut self) -> Result<Activity> {
+        let query = ActivityQuery {
+            detail: self.detail,
+        };
+        let Some(resp) = self.client.try_send(&query.encode()?)? else {
+            if self.detail == Privacy::Timestamps {
+                bail!("actived refused the query");
+            }
+            warn!("actived doesn't expose the input intensity, start it with `--privacy counts` for adaptive timers");
+            self.detail = Privacy::Timestamps;
+            return self.query();
+        };
+        let info = ActivityInfo::decode(&resp)?;
+        Ok(Activity {
+            input_elapsed: info.input_elapsed,
+            intensity: (self.detail >= Privacy::Counts).then_some(info.intensity),
+        })
+    }
+}
+
+/// Idle time of the X server from the screen saver extension
+#[cfg(feature = "x11")]
+struct X11 {
+    conn: RustConnection,
+    root: u32,
+}
+
+#[cfg(feature = "x11")]
+impl X11 {
+    fn connect() -> Result<Self> {
+        let (conn, screen) = x11rb::connect(None).context("Failed to connect to X server")?;
+        if conn
+            .extension_information(screensaver::X11_EXTENSION_NAME)?
+            .is_none()
+        {
+            bail!("X server doesn't support the screen saver extension");
+        }
+        let root = conn.setup().roots[screen].root;
+        Ok(Self { conn, root })
+    }
+}
+
+#[cfg(feature = "x11")]
+impl ActivityBackend for X11 {
+    fn query(&mut self) -> Result<Activity> {
+        let info = screensaver::query_info(&self.conn, self.root)?.reply()?;
+        Ok(Activity {
+            input_elapsed: Duration::from_millis(info.ms_since_user_input.into()),
+            intensity: None,
+        })
+    }
+}
+
+/// Result of a query that runs in its own thread, for queries that are too slow to run while
+/// the state is locked, like the ones that run a process
+///
+/// The thread stops once the result is no longer used.
+pub(crate) struct Polled<T> {
+    latest: Arc<Mutex<Result<T, String>>>,
+}
+
+impl<T: Clone + Send + 'static> Polled<T> {
+    /// Repeats the query every `interval`, starting with the given result
+    pub(crate) fn start(
+        interval: Duration,
+        first: T,
+        mut query: impl FnMut() -> Result<T> + Send + 'static,
+    ) -> Self {
+        let latest = Arc::new(Mutex::new(Ok(first)));
+        let weak = Arc::downgrade(&latest);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let result = query().map_err(|e| format!("{e:#}"));
+            let Some(latest) = weak.upgrade() else {
+                break;
+            };
+            *latest.lock() = result;
+        });
+        Self { latest }
+    }
+
+    pub(crate) fn latest(&self) -> Result<T> {
+        self.latest.lock().clone().map_err(|e| anyhow!(e))
+    }
+}
+
+/// The activity lags behind by up to the poll interval, which is short compared to the
+/// inactivity pause
+impl ActivityBackend for Polled<Activity> {
+    fn query(&mut self) -> Result<Activity> {
+        self.latest()
+    }
+}
+
+/// Idle hint of the session from systemd-logind, only set after the idle timeout of the desktop
+fn logind() -> Result<Activity> {
+    let output = Command::new("loginctl")
+        .args([
+            "show-session",
+            "auto",
+            "--property=IdleHint",
+            "--property=IdleSinceHintMonotonic",
+        ])
+        .output()
+        .context("Failed to run loginctl")?;
+    if !output.status.success() {
+        bail!(
+            "loginctl failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let stdout = String::from_utf8(output.stdout)?;
+    let mut idle = false;
+    let mut idle_since = 0;
+    for line in stdout.lines() {
+        match line.split_once('=') {
+            Some(("IdleHint", value)) => idle = value == "yes",
+            Some(("IdleSinceHintMonotonic", value)) => idle_since = value.parse::<u64>()?,
+            _ => {}
+        }
+    }
+    let input_elapsed = if idle {
+        monotonic_now()?.saturating_sub(Duration::from_micros(idle_since))
+    } else {
+        Duration::ZERO
+    };
+    Ok(Activity {
+        input_elapsed,
+        intensity: None,
+    })
+}
+
+/// Current time of the monotonic clock, which logind uses for its monotonic timestamps
+fn monotonic_now() -> Result<Duration> {
+    read_clock(libc::CLOCK_MONOTONIC)
+}
+
+/// Time since boot including suspends, unlike the monotonic clock it keeps running while the
+/// system is suspended, and unlike the system time it never jumps
+pub fn boot_time() -> Result<Duration> {
+    read_clock(libc::CLOCK_BOOTTIME)
+}
+
+fn read_clock(clock: libc::clockid_t) -> Result<Duration> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: the timespec is a valid pointer to write to
+    if unsafe { libc::clock_gettime(clock, &mut ts) } != 0 {
+        bail!(
+            "Failed to read clock {clock}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+fn session_bus() -> Result<zbus::blocking::Connection> {
+    zbus::blocking::Connection::session().context("Failed to connect to the session bus")
+}
+
+/// Idle time in milliseconds from a method of the desktop on the session bus
+struct DBusIdle {
+    connection: zbus::blocking::Connection,
+    dest: &'static str,
+    path: &'static str,
+    interface: &'static str,
+    method: &'static str,
+}
+
+impl DBusIdle {
+    fn query(&self) -> Result<Activity> {
+        let reply = self
+            .connection
+            .call_method(
+                Some(self.dest),
+                self.path,
+                Some(self.interface),
+                self.method,
+                &(),
+            )
+            .with_context(|| format!("Failed to call {}.{}", self.interface, self.method))?;
+        let body = reply.body();
+        // GNOME replies with 64 bits and KDE with 32 bits
+        let millis = match body.deserialize::<(u64,)>() {
+            Ok((millis,)) => millis,
+            Err(_) => body
+                .deserialize::<(u32,)>()
+                .map(|(millis,)| millis.into())
+                .with_context(|| format!("Unexpected reply from {}", self.method))?,
+        };
+        Ok(Activity {
+            input_elapsed: Duration::from_millis(millis),
+            intensity: None,
+        })
+    }
+}
+
+/// Default time between runs of the presence command
+const PRESENCE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// User-supplied command that reports presence with its exit code
+pub struct PresenceHook {
+    command: String,
+    interval: Duration,
+    last_run: Option<Instant>,
+    present: bool,
+}
+
+impl PresenceHook {
+    pub fn new(command: String, interval: Option<Duration>) -> Self {
+        info!("Using presence command '{command}'");
+        Self {
+            command,
+            interval: interval.unwrap_or(PRESENCE_INTERVAL),
+            last_run: None,
+            present: false,
+        }
+    }
+
+    /// Returns whether the user was present at the last run, runs the command when due
+    pub fn present(&mut self) -> bool {
+        if self.last_run.is_none_or(|t| t.elapsed() >= self.interval) {
+            self.last_run = Some(Instant::now());
+            let present = match Command::new("sh")
+                .args(["-c", &self.command])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .status()
+            {
+                Ok(status) => status.success(),
+                Err(e) => {
+                    warn!("Failed to run presence command: {e}");
+                    false
+                }
+            };
+            if present != self.present {
+                info!("Presence changed to {present}");
+            }
+            self.present = present;
+        }
+        self.present
+    }
+}
+
+/// Audio playback, checked in the background
+pub struct AudioMonitor(Polled<bool>);
+
+impl AudioMonitor {
+    pub fn start() -> Self {
+        Self(Polled::start(AUDIO_POLL, false, || {
+            Ok(audio_playing().unwrap_or_else(|e| {
+                warn!("Failed to check audio playback: {e:#}");
+                false
+            }))
+        }))
+    }
+
+    /// Whether audio was playing at the last check
+    pub fn playing(&self) -> bool {
+        self.0.latest().unwrap_or_default()
+    }
+}
+
+/// Checks whether any application is playing audio, using PulseAudio or PipeWire
+fn audio_playing() -> Result<bool> {
+    let output = Command::new("pactl")
+        .args(["list", "sink-inputs"])
+        .output()
+        .context("Failed to run pactl")?;
+    if !output.status.success() {
+        bail!("pactl failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .any(|line| line.trim() == "Corked: no"))
+}
+
+/// Returns whether the graphical session is used remotely, e.g. through XRDP or VNC
+pub fn remote_session() -> bool {
+    if std::env::var_os("XRDP_SESSION").is_some() {
+        return true;
+    }
+    let logind_remote = Command::new("loginctl")
+        .args(["show-session", "auto", "--property=Remote", "--value"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .is_some_and(|output| output.stdout.trim_ascii() == b"yes");
+    if logind_remote {
+        return true;
+    }
+    // Remote desktop servers such as xrdp and TigerVNC use display numbers from 10 upwards
+    std::env::var("DISPLAY").is_ok_and(|display| {
+        display
+            .strip_prefix(':')
+            .and_then(|d| d.split('.').next())
+            .and_then(|d| d.parse::<u32>().ok())
+            .is_some_and(|n| n >= 10)
+    })
+}
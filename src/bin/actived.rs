@@ -1,31 +1,188 @@
+// Without the evdev feature only the explanation in main remains
+#![cfg_attr(not(feature = "evdev"), allow(dead_code, unused_imports))]
 use anyhow::Result;
-use movebeam::{msg::Encoding, socket::SocketServer};
+use clap::Parser;
+use movebeam::{
+    activity::InputEvent,
+    config::Logging,
+    msg::{ActivityInfo, ActivityMetrics, ActivityQuery, Encoding, Privacy},
+    recording::TraceWriter,
+    socket::SocketServer,
+};
 use parking_lot::Mutex;
-use std::{sync::Arc, thread, time::SystemTime};
-use tracing_subscriber::{filter::EnvFilter, fmt, prelude::*};
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use tracing::{error, info, warn};
 
+/// Window over which the input intensity is measured
+const INTENSITY_WINDOW: Duration = Duration::from_secs(60);
+/// Interval at which recorded events are flushed to the trace file
+const RECORD_FLUSH: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// Record the timestamps of input events to a trace file
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+    /// Most detailed information exposed to clients, more detailed queries are refused
+    #[arg(long, value_enum, default_value_t = Privacy::Timestamps)]
+    pub privacy: Privacy,
+    /// Don't restrict the filesystem access and system calls after initialization
+    #[arg(long)]
+    pub no_sandbox: bool,
+    /// Switch to this user after opening the input devices, only it and its group can
+    /// connect to the socket, e.g. `movebeam`
+    #[arg(long)]
+    pub user: Option<String>,
+}
+
+struct ActivityState {
+    last_input: Instant,
+    /// Events per kind within the intensity window, only kept when counts are exposed
+    keyboard_events: VecDeque<Instant>,
+    mouse_events: VecDeque<Instant>,
+    gamepad_events: VecDeque<Instant>,
+    privacy: Privacy,
+}
+
+impl ActivityState {
+    fn new(privacy: Privacy) -> Self {
+        Self {
+            last_input: Instant::now(),
+            keyboard_events: VecDeque::new(),
+            mouse_events: VecDeque::new(),
+            gamepad_events: VecDeque::new(),
+            privacy,
+        }
+    }
+
+    fn record(&mut self, event: InputEvent) {
+        self.last_input = Instant::now();
+        let events = match (self.privacy, event) {
+            (Privacy::Timestamps, _) => return,
+            (_, InputEvent::Keyboard) => &mut self.keyboard_events,
+            (Privacy::Counts, _) => return,
+            (_, InputEvent::Mouse) => &mut self.mouse_events,
+            (_, InputEvent::Gamepad) => &mut self.gamepad_events,
+        };
+        events.push_back(Instant::now());
+        self.expire();
+    }
+
+    fn expire(&mut self) {
+        for events in [
+            &mut self.keyboard_events,
+            &mut self.mouse_events,
+            &mut self.gamepad_events,
+        ] {
+            while events
+                .front()
+                .is_some_and(|t| t.elapsed() > INTENSITY_WINDOW)
+            {
+                events.pop_front();
+            }
+        }
+    }
+
+    fn per_minute(events: &VecDeque<Instant>) -> f64 {
+        events.len() as f64 * 60.0 / INTENSITY_WINDOW.as_secs_f64()
+    }
+
+    /// Encodes the response to a query, which is refused if it is more detailed than allowed
+    fn respond(&mut self, query: &ActivityQuery) -> Option<Vec<u8>> {
+        if query.detail > self.privacy {
+            warn!("Refused query for {:?} information", query.detail);
+            return None;
+        }
+        self.expire();
+        let info = ActivityInfo {
+            input_elapsed: self.last_input.elapsed(),
+            intensity: Self::per_minute(&self.keyboard_events),
+        };
+        if query.detail == Privacy::Full {
+            ActivityMetrics {
+                info,
+                mouse: Self::per_minute(&self.mouse_events),
+                gamepad: Self::per_minute(&self.gamepad_events),
+            }
+            .encode()
+            .ok()
+        } else {
+            info.encode().ok()
+        }
+    }
+}
+
+#[cfg(not(feature = "evdev"))]
 fn main() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::builder().from_env().unwrap())
-        .init();
+    Args::parse();
+    anyhow::bail!("actived was built without the evdev feature, so it can't read the input devices")
+}
+
+#[cfg(feature = "evdev")]
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let _log_guard = movebeam::logging::init(&Logging::default())?;
     let (event_tx, event_rx) = crossbeam_channel::bounded(128);
 
-    let last_input = Arc::new(Mutex::new(SystemTime::now()));
+    info!("Exposing {:?} activity information", args.privacy);
+    let activity = Arc::new(Mutex::new(ActivityState::new(args.privacy)));
+    let mut recorder = args
+        .record
+        .map(|path| TraceWriter::create(&path))
+        .transpose()?;
+
+    let devices = movebeam::input_listener::open_devices();
+    let socket_path = movebeam::activity_daemon_socket();
+    let mut socket = SocketServer::create(socket_path.clone(), true)?;
+    if let Some(user) = &args.user {
+        movebeam::sandbox::drop_privileges(user, &socket_path)?;
+    }
+    if args.no_sandbox {
+        warn!("Running without sandbox");
+    } else if let Err(e) = movebeam::sandbox::restrict() {
+        warn!("Failed to sandbox, continuing without: {e:#}");
+    }
+
     {
-        let last_input = last_input.clone();
+        let activity = activity.clone();
         thread::spawn(move || {
-            movebeam::input_listener::start_listener(event_tx);
+            movebeam::input_listener::start_listener(devices, event_tx);
         });
-        thread::spawn(move || loop {
-            if event_rx.recv().is_ok() {
-                *last_input.lock() = SystemTime::now();
+        thread::spawn(move || {
+            let mut last_flush = Instant::now();
+            loop {
+                if let Ok(event) = event_rx.recv() {
+                    if let Some(recorder) = &mut recorder {
+                        if let Err(e) = recorder.record(SystemTime::now(), &event) {
+                            error!("Failed to record event: {e}");
+                        }
+                        if last_flush.elapsed() >= RECORD_FLUSH {
+                            if let Err(e) = recorder.flush() {
+                                error!("Failed to flush trace file: {e}");
+                            }
+                            last_flush = Instant::now();
+                        }
+                    }
+                    activity.lock().record(event);
+                }
             }
         });
     }
 
-    let mut socket = SocketServer::create(movebeam::activity_daemon_socket(), true)?;
-    socket.serve(|_| Some(last_input.lock().encode().unwrap()))?;
+    socket.serve(|msg| match ActivityQuery::decode(msg) {
+        Ok(query) => activity.lock().respond(&query),
+        Err(e) => {
+            error!("Invalid query: {e}");
+            None
+        }
+    })?;
 
     Ok(())
 }
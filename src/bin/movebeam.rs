@@ -1,74 +1,792 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Local, Utc};
 use clap::Parser;
+use crossbeam_channel::{Receiver, RecvTimeoutError};
 use movebeam::{
-    cli::{Cli, CliCommand},
-    msg::{Encoding, Message, Response, ResponseError},
-    socket::SocketClient,
+    cli::{Cli, CliCommand, InitSystem, StatsCommand, TrackerFormat},
+    config::{ActivitySource, BarStyle, Config},
+    format::{
+        ascii_bar, bar, csv, format_duration, format_iso8601, format_short, paint, porcelain,
+        Formatter, CSV_HEADER,
+    },
+    msg::{
+        ActivityInfo, ActivityQuery, DaemonStatus, Encoding, ListQuery, Message, Privacy, Response,
+        ResponseError, StatusHeader, TimerInfo,
+    },
+    socket::{Peer, SocketClient},
+    tr,
 };
-use std::time::Duration;
-use std::{io::Write, time::SystemTime};
+use std::time::{Duration, Instant};
+use std::{
+    ffi::CString,
+    fs,
+    io::Write,
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+    process::ExitCode,
+    thread,
+    time::SystemTime,
+};
+
+/// Interval at which a followed bar is printed while the timers don't change
+const FOLLOW_REFRESH: Duration = Duration::from_secs(1);
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     let args = Cli::parse();
 
-    let mut client = SocketClient::connect(movebeam::daemon_socket())?;
-    let msg: Message = args.cmd.clone().into();
-    let res_bytes = client.send(&msg.encode()?)?;
-    let response = Response::decode(&res_bytes).with_context(|| "")?;
+    let mut config = movebeam::config_path()
+        .and_then(|path| Config::load_or_default(&path))
+        .unwrap_or_default();
+    // The doctor reports an invalid configuration, so it still runs in the language of the
+    // environment then
+    let language = movebeam::i18n::init(&config.language);
+    if let CliCommand::Doctor = args.cmd {
+        return doctor();
+    }
+    language?;
+
+    match &args.cmd {
+        CliCommand::Wait { name, timeout } => {
+            wait(name, *timeout)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        CliCommand::Service { init } => {
+            service(*init)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        CliCommand::Idle => {
+            idle()?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        CliCommand::Stats {
+            cmd: Some(StatsCommand::Purge { older_than, all }),
+            ..
+        } => {
+            purge_history(*older_than, *all)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        CliCommand::Stats {
+            export: Some(format),
+            tag,
+            days,
+            ..
+        } => {
+            export_history(*format, tag, *days)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        CliCommand::Stats { heatmap, days, .. } => {
+            stats(&config, *heatmap, *days, args.csv)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        CliCommand::Goals => {
+            goals(&config)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        _ => {}
+    }
+
+    let fmt = Formatter::new(&config.format);
+    let remaining = args.remaining || config.cli.remaining;
+    let ascii = args.ascii || config.cli.ascii;
+
+    let color = !ascii && args.color.enabled();
+
+    if let CliCommand::IdleBar {
+        threshold,
+        size,
+        fill,
+        empty,
+        left,
+        right,
+    } = &args.cmd
+    {
+        let (fill, empty, left, right) = if ascii {
+            (
+                ascii_bar(fill),
+                ascii_bar(empty),
+                ascii_bar(left),
+                ascii_bar(right),
+            )
+        } else {
+            (&**fill, &**empty, &**left, &**right)
+        };
+        let percentage = query_idle()?.as_secs_f64() / threshold.as_secs_f64();
+        let bar_str = paint(
+            &bar(percentage, *size, fill, empty),
+            percentage,
+            percentage >= 1.0,
+            color,
+        );
+        println!("{left}{bar_str}{right}");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let token = match &args.remote {
+        Some(_) => args.token.clone(),
+        None if args.control => Some(
+            std::fs::read_to_string(movebeam::daemon_control_token()?)
+                .context("Failed to read the control token")?,
+        ),
+        None => None,
+    };
+    let token = token.as_deref();
+    let connect = || match &args.remote {
+        Some(remote) => SocketClient::connect_tcp(remote),
+        None if args.control => SocketClient::connect(movebeam::daemon_control_socket()?)
+            .context("Failed to connect to the control socket, is moved read-only?"),
+        None => SocketClient::connect(movebeam::daemon_socket()?),
+    };
+    let mut client = connect()?;
+    let msg = Message::try_from(args.cmd.clone())?;
+    let res_bytes = client.send(&authenticate(msg.clone(), token).encode()?)?;
+    let response = match Response::decode(&res_bytes) {
+        Ok(Response::Error(ResponseError::UnknownMessage)) => {
+            return Err(mismatch(&mut client, &msg, token))
+        }
+        Ok(response) => response,
+        Err(_) if matches!(msg, Message::Status) => {
+            // The leading fields of the status decode across versions
+            let status = StatusHeader::decode_response(&res_bytes)
+                .map_err(|_| mismatch(&mut client, &msg, token))?;
+            println!("moved {}", status.version);
+            println!(
+                "{}",
+                tr!(
+                    "status-capabilities",
+                    capabilities = status.capabilities.join(", ")
+                )
+            );
+            eprintln!(
+                "WARNING: {}",
+                tr!(
+                    "status-version-differs",
+                    daemon = status.version,
+                    client = movebeam::VERSION
+                )
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+        Err(_) => return Err(mismatch(&mut client, &msg, token)),
+    };
     let mut stdout = std::io::stdout().lock();
     match response {
         Response::Ok => {}
-        Response::Duration(d) => writeln!(stdout, "{}", format_duration(d))?,
-        Response::Error(e) => match e {
-            ResponseError::NotFound => writeln!(stdout, "ERROR: Timer not found!")?,
-        },
+        Response::Duration(d) => writeln!(stdout, "{}", fmt.duration(d))?,
+        // The prefix stays untranslated for scripts that look for it
+        Response::Error(e) => {
+            writeln!(stdout, "ERROR: {}", error_message(e, args.remote.is_some()))?
+        }
+        Response::Status(status) => {
+            writeln!(stdout, "moved {}", status.version)?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "status-capabilities",
+                    capabilities = status.capabilities.join(", ")
+                )
+            )?;
+            match (status.activity_source, status.activity_age) {
+                (Some(source), Some(age)) => writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "status-activity-age",
+                        source = source.to_string(),
+                        age = fmt.duration(age)
+                    )
+                )?,
+                (Some(source), None) => writeln!(
+                    stdout,
+                    "{}",
+                    tr!("status-activity", source = source.to_string())
+                )?,
+                _ => {}
+            }
+            let session = if status.remote_session {
+                tr!("status-session-remote")
+            } else {
+                tr!("status-session-local")
+            };
+            writeln!(stdout, "{session}")?;
+            if let Some(app) = status.focused_app {
+                writeln!(stdout, "{}", tr!("status-focused-app", app = app))?;
+            }
+            if !status.notifications_available {
+                writeln!(stdout, "{}", tr!("status-notifications-unavailable"))?;
+            }
+            if let Some(next) = status.next {
+                writeln!(stdout, "{}", next_summary(next, &fmt))?;
+            }
+            if !status.audit.is_empty() {
+                writeln!(stdout, "{}", tr!("status-changes"))?;
+            }
+            for entry in status.audit {
+                let time = DateTime::from_timestamp(entry.time as i64, 0)
+                    .map(|t| fmt.time_seconds(&t.with_timezone(&Local)))
+                    .unwrap_or_default();
+                let peer = Peer {
+                    uid: entry.uid,
+                    pid: entry.pid,
+                };
+                let process = entry.process.unwrap_or_else(|| tr!("status-exited"));
+                writeln!(
+                    stdout,
+                    "  {}",
+                    tr!(
+                        "status-change",
+                        time = time,
+                        message = entry.message,
+                        peer = peer.to_string(),
+                        process = process
+                    )
+                )?;
+            }
+        }
+        Response::Batch(_) | Response::Reply(..) | Response::Changed(_) => {
+            bail!("Unexpected response: {response:?}")
+        }
+        Response::State(snapshots) => {
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&snapshots)?)?
+        }
+        Response::List(list)
+            if matches!(
+                args.cmd,
+                CliCommand::Reset { dry_run: true, .. }
+                    | CliCommand::ResetAll { dry_run: true, .. }
+            ) =>
+        {
+            if list.is_empty() {
+                writeln!(stdout, "{}", tr!("reset-none"))?;
+            }
+            for (name, info) in list {
+                let time = format!(
+                    "{}/{}",
+                    fmt.duration(info.elapsed),
+                    fmt.duration(info.interval)
+                );
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "reset-would",
+                        name = name,
+                        time = time,
+                        status = info.status.to_string()
+                    )
+                )?;
+            }
+        }
         Response::List(list) => {
+            if args.csv {
+                writeln!(stdout, "{CSV_HEADER}")?;
+            }
             for (name, info) in list {
+                if args.csv {
+                    writeln!(stdout, "{}", csv(&name, &info))?;
+                    continue;
+                }
+                if args.porcelain {
+                    writeln!(stdout, "{}", porcelain(&name, &info))?;
+                    continue;
+                }
+                let percentage = info.elapsed.as_secs_f64() / info.interval.as_secs_f64();
+                let time = format!(
+                    "{}/{}",
+                    fmt.duration(info.elapsed),
+                    fmt.duration(info.interval)
+                );
                 writeln!(
                     stdout,
-                    "{}\t{}/{}",
+                    "{}\t{}\t{}",
                     name,
-                    format_duration(info.elapsed),
-                    format_duration(info.interval)
+                    paint(&time, percentage, info.overdue, color),
+                    info.status
                 )?;
             }
+            let summary =
+                matches!(args.cmd, CliCommand::List { summary: true, .. }) || config.cli.summary;
+            if summary && !args.csv && !args.porcelain {
+                let resp = client.send(&authenticate(Message::Status, token).encode()?)?;
+                // A daemon of another version may have a status without the next timer
+                if let Ok(Response::Status(DaemonStatus {
+                    next: Some(next), ..
+                })) = Response::decode(&resp)
+                {
+                    writeln!(stdout, "{}", next_summary(next, &fmt))?;
+                }
+            }
+        }
+        Response::Details(info, details) => {
+            writeln!(
+                stdout,
+                "{}\t{}/{} ({})",
+                details.name,
+                fmt.duration(info.elapsed),
+                fmt.duration(info.interval),
+                info.status
+            )?;
+            if let Some(deferred) = info.deferred {
+                writeln!(stdout, "{}", held_back(deferred, &fmt))?;
+            }
+            writeln!(stdout, "{}", today(&info, &fmt))?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!("timer-source", source = details.source.to_string())
+            )?;
+            if let Some(configured) = details.configured_name {
+                writeln!(stdout, "{}", tr!("timer-renamed", name = configured))?;
+            }
+            if let Some(template) = details.extends {
+                writeln!(stdout, "{}", tr!("timer-extends", name = template))?;
+            }
+            for (name, value) in details.settings {
+                writeln!(stdout, "{name}: {value}")?;
+            }
         }
         Response::Timer(info) => {
+            let get = matches!(args.cmd, CliCommand::Get { .. }) && !args.porcelain;
             if let CliCommand::Bar {
-                name: _,
+                name,
+                style,
                 size,
                 fill,
                 empty,
                 left,
                 right,
                 blink,
+                label,
+                show_time,
+                follow,
             } = args.cmd
             {
-                let percentage =
-                    (info.elapsed.as_secs_f64() / info.interval.as_secs_f64()).min(1.0);
-                let bar_str = if percentage >= 1.0 && blink {
-                    let unix_time = SystemTime::now()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs();
-                    if unix_time % 2 == 0 {
-                        // Blink on
-                        fill.repeat(size)
+                let style = match style {
+                    Some(name) => config
+                        .bar
+                        .styles
+                        .remove(&name)
+                        .with_context(|| format!("No bar style {name} in the configuration"))?,
+                    None => BarStyle::default(),
+                };
+                let size = size.or(style.size).unwrap_or(16);
+                let fill = fill.or(style.fill).unwrap_or_else(|| "█".to_string());
+                let empty = empty.or(style.empty).unwrap_or_else(|| "░".to_string());
+                let left = left.or(style.left).unwrap_or_else(|| "▕".to_string());
+                let right = right.or(style.right).unwrap_or_else(|| "▏".to_string());
+                let blink = blink || style.blink;
+                let label = label.or(style.label);
+                let show_time = show_time || style.show_time;
+                let color = style.colors.map_or(color, |colors| colors && !ascii);
+                let (fill, empty, left, right) = if ascii {
+                    (
+                        ascii_bar(&fill),
+                        ascii_bar(&empty),
+                        ascii_bar(&left),
+                        ascii_bar(&right),
+                    )
+                } else {
+                    (&*fill, &*empty, &*left, &*right)
+                };
+                let render = |info: &TimerInfo| {
+                    let mut percentage =
+                        (info.elapsed.as_secs_f64() / info.interval.as_secs_f64()).min(1.0);
+                    if remaining {
+                        percentage = 1.0 - percentage;
+                    }
+                    let bar_str = if info.overdue && blink {
+                        if info.blink {
+                            fill.repeat(size)
+                        } else {
+                            empty.repeat(size)
+                        }
                     } else {
-                        // Blink off
-                        empty.repeat(size)
+                        bar(percentage, size, fill, empty)
+                    };
+                    let bar_str = paint(
+                        &bar_str,
+                        info.elapsed.as_secs_f64() / info.interval.as_secs_f64(),
+                        info.overdue,
+                        color,
+                    );
+                    let mut line = format!("{left}{bar_str}{right}");
+                    if let Some(label) = &label {
+                        line = format!("{label} {line}");
+                    }
+                    if show_time {
+                        let time = if info.overdue {
+                            format!(
+                                "+{}",
+                                format_short(info.elapsed.saturating_sub(info.interval))
+                            )
+                        } else {
+                            format_short(info.interval.saturating_sub(info.elapsed))
+                        };
+                        line = format!("{line} {time}");
                     }
+                    line
+                };
+                writeln!(stdout, "{}", render(&info))?;
+                if follow {
+                    let changes = subscribe(connect()?, token)?;
+                    let get = authenticate(Message::Get(name), token).encode()?;
+                    loop {
+                        match changes.recv_timeout(FOLLOW_REFRESH) {
+                            Ok(changed) => {
+                                changed?;
+                            }
+                            Err(RecvTimeoutError::Timeout) => {}
+                            Err(RecvTimeoutError::Disconnected) => {
+                                bail!("Lost the connection to moved")
+                            }
+                        }
+                        match Response::decode(&client.send(&get)?)? {
+                            Response::Timer(info) => writeln!(stdout, "{}", render(&info))?,
+                            Response::Error(e) => {
+                                bail!("{}", error_message(e, args.remote.is_some()))
+                            }
+                            response => bail!("Unexpected response: {response:?}"),
+                        }
+                    }
+                }
+            } else if let (true, CliCommand::Get { name, .. }) = (args.porcelain, &args.cmd) {
+                writeln!(stdout, "{}", porcelain(name, &info))?;
+            } else if remaining {
+                let line = if info.elapsed > info.interval {
+                    tr!(
+                        "timer-overdue",
+                        time = fmt.duration(info.elapsed - info.interval),
+                        status = info.status.to_string()
+                    )
                 } else {
-                    let fill_count = (size as f64 * percentage).round() as usize;
-                    fill.repeat(fill_count) + &empty.repeat(size - fill_count)
+                    tr!(
+                        "timer-left",
+                        time = fmt.duration(info.interval - info.elapsed),
+                        status = info.status.to_string()
+                    )
                 };
-                writeln!(stdout, "{}{}{}", left, bar_str, right)?;
+                writeln!(stdout, "{line}")?;
             } else {
                 writeln!(
                     stdout,
-                    "{}/{}",
-                    format_duration(info.elapsed),
-                    format_duration(info.interval)
+                    "{}/{} ({})",
+                    fmt.duration(info.elapsed),
+                    fmt.duration(info.interval),
+                    info.status
+                )?;
+            }
+            if let (Some(deferred), true) = (info.deferred, get) {
+                writeln!(stdout, "{}", held_back(deferred, &fmt))?;
+            }
+            if get {
+                writeln!(stdout, "{}", today(&info, &fmt))?;
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Explains why the daemon didn't understand the message or its response couldn't be decoded,
+/// which happens when moved and movebeam are of different versions
+fn mismatch(client: &mut SocketClient, msg: &Message, token: Option<&str>) -> anyhow::Error {
+    let status = authenticate(Message::Status, token)
+        .encode()
+        .and_then(|status| client.send(&status))
+        .and_then(|resp| StatusHeader::decode_response(&resp));
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => {
+            return anyhow!(
+                "moved doesn't understand movebeam {}, restart it to update it: {e:#}",
+                movebeam::VERSION
+            )
+        }
+    };
+    if !status.capabilities.iter().any(|c| c == msg.name()) {
+        anyhow!(
+            "moved {} doesn't support '{}', restart moved to update it",
+            status.version,
+            msg.name()
+        )
+    } else if status.version != movebeam::VERSION {
+        anyhow!(
+            "moved {} doesn't understand movebeam {}, restart moved to update it",
+            status.version,
+            movebeam::VERSION
+        )
+    } else {
+        anyhow!("Failed to decode the response of moved")
+    }
+}
+
+/// Describes the timer that is due next, e.g. `next: move in 03:12`
+fn next_summary((name, remaining): (String, Duration), fmt: &Formatter) -> String {
+    if remaining.is_zero() {
+        tr!("next-now", name = name)
+    } else {
+        tr!("next-in", name = name, time = fmt.duration(remaining))
+    }
+}
+
+/// Describes how long the notification of a timer is held back, and at most
+fn held_back((deferred, cap): (Duration, Duration), fmt: &Formatter) -> String {
+    tr!(
+        "timer-held-back",
+        deferred = fmt.duration(deferred),
+        cap = fmt.duration(cap)
+    )
+}
+
+/// Describes how often the timer went off today and how long it ran
+fn today(info: &TimerInfo, fmt: &Formatter) -> String {
+    tr!(
+        "timer-today",
+        count = info.fired_today,
+        total = fmt.total(info.elapsed_today)
+    )
+}
+
+/// Describes an error of the daemon in the configured language
+fn error_message(e: ResponseError, remote: bool) -> String {
+    match e {
+        ResponseError::NotFound => tr!("error-not-found"),
+        ResponseError::MissingDuration => tr!("error-missing-duration"),
+        ResponseError::Unsupported => tr!("error-unsupported"),
+        ResponseError::AlreadyExists => tr!("error-already-exists"),
+        ResponseError::ReadOnly if remote => tr!("error-read-only-remote"),
+        ResponseError::ReadOnly => tr!("error-read-only"),
+        ResponseError::Unauthorized => tr!("error-unauthorized"),
+        ResponseError::Similar(names) => tr!("error-similar", names = quote_names(&names)),
+        ResponseError::NothingToUndo => tr!("error-nothing-to-undo"),
+        ResponseError::NotResettable => tr!("error-not-resettable"),
+        ResponseError::NoBreaks => tr!("error-no-breaks"),
+        ResponseError::UnknownMessage => tr!("error-unknown-message"),
+    }
+}
+
+/// Lists names as `'a', 'b' or 'c'`
+fn quote_names(names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|n| format!("'{n}'")).collect();
+    match quoted.as_slice() {
+        [init @ .., last] if !init.is_empty() => format!("{} or {last}", init.join(", ")),
+        _ => quoted.join(""),
+    }
+}
+
+/// Adds the token for a remote daemon or the control socket to the message
+fn authenticate(msg: Message, token: Option<&str>) -> Message {
+    match token {
+        Some(token) => Message::Authenticated(token.to_string(), Box::new(msg)),
+        None => msg,
+    }
+}
+
+/// Blocks until the timer goes off, which the daemon tells its subscribers right away
+fn wait(name: &str, timeout: Option<Duration>) -> Result<()> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    // Subscribed before the query, so the timer can't go off in between unnoticed
+    let changes = subscribe(SocketClient::connect(movebeam::daemon_socket()?)?, None)?;
+    let mut client = SocketClient::connect(movebeam::daemon_socket()?)?;
+    // The details have the full name of the timer, which the changes are listed by
+    let name =
+        match Response::decode(&client.send(&Message::Describe(name.to_string()).encode()?)?)? {
+            Response::Details(info, _) if info.elapsed >= info.interval => return Ok(()),
+            Response::Details(_, details) => details.name,
+            Response::Error(e) => bail!("{}", error_message(e, false)),
+            response => bail!("Unexpected response: {response:?}"),
+        };
+    loop {
+        let changed = match deadline {
+            Some(deadline) => changes.recv_deadline(deadline).map_err(|e| match e {
+                RecvTimeoutError::Timeout => anyhow!("Timed out waiting for timer {name}"),
+                RecvTimeoutError::Disconnected => anyhow!("Lost the connection to moved"),
+            })?,
+            None => changes
+                .recv()
+                .map_err(|_| anyhow!("Lost the connection to moved"))?,
+        }?;
+        let went_off = changed
+            .iter()
+            .any(|(timer, info)| *timer == name && info.elapsed >= info.interval);
+        if went_off {
+            return Ok(());
+        }
+    }
+}
+
+/// The timers that changed, by name, as pushed to a subscriber
+type Changes = Receiver<Result<Vec<(String, TimerInfo)>>>;
+
+/// Subscribes to the changes of the timers, which are received in the background
+fn subscribe(mut client: SocketClient, token: Option<&str>) -> Result<Changes> {
+    let msg = authenticate(Message::Subscribe, token);
+    match Response::decode(&client.send(&msg.encode()?)?)? {
+        Response::Ok => {}
+        Response::Error(ResponseError::UnknownMessage) => {
+            return Err(mismatch(&mut client, &Message::Subscribe, token))
+        }
+        Response::Error(e) => bail!("{}", error_message(e, false)),
+        response => bail!("Unexpected response: {response:?}"),
+    }
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    thread::spawn(move || loop {
+        let changed = client.receive().and_then(|frame| {
+            let frame = frame.context("moved closed the connection")?;
+            match Response::decode(&frame)? {
+                Response::Changed(changed) => Ok(changed),
+                response => bail!("Unexpected response: {response:?}"),
+            }
+        });
+        let failed = changed.is_err();
+        if sender.send(changed).is_err() || failed {
+            break;
+        }
+    });
+    Ok(receiver)
+}
+
+/// Prints the idle time reported by the activity daemon in the format of xprintidle
+fn idle() -> Result<()> {
+    println!("{}", query_idle()?.as_millis());
+    Ok(())
+}
+
+/// Returns the time since the last input from moved, or from the activity daemon if moved
+/// doesn't track the activity
+fn query_idle() -> Result<Duration> {
+    let from_daemon = movebeam::daemon_socket()
+        .and_then(SocketClient::connect)
+        .and_then(|mut client| Response::decode(&client.send(&Message::Inactive.encode()?)?));
+    if let Ok(Response::Duration(elapsed)) = from_daemon {
+        return Ok(elapsed);
+    }
+    let mut client = SocketClient::connect(movebeam::activity_daemon_socket())
+        .context("Failed to connect to the activity daemon")?;
+    let query = ActivityQuery {
+        detail: Privacy::Timestamps,
+    };
+    Ok(ActivityInfo::decode(&client.send(&query.encode()?)?)?.input_elapsed)
+}
+
+/// Prints the activity per day or per hour of the day from the history
+fn stats(config: &Config, heatmap: bool, days: u32, csv: bool) -> Result<()> {
+    let periods = movebeam::history::read(&movebeam::history_path()?)?;
+    let fmt = Formatter::new(&config.format);
+    // CSV uses ISO 8601 durations like the timer list, for spreadsheets and scripts
+    let total = |d: Duration| {
+        if csv {
+            format_iso8601(d)
+        } else {
+            fmt.total(d)
+        }
+    };
+    let sep = if csv { "," } else { "\t" };
+    // The header of CSV stays untranslated for scripts
+    let (date, active, longest) = if csv {
+        (
+            "date".to_string(),
+            "active".to_string(),
+            "longest".to_string(),
+        )
+    } else {
+        (tr!("stats-date"), tr!("stats-active"), tr!("stats-longest"))
+    };
+    let mut stdout = std::io::stdout().lock();
+    if heatmap {
+        let hours: Vec<String> = (0..24).map(|h| format!("{h:02}")).collect();
+        writeln!(stdout, "{date}{sep}{}", hours.join(sep))?;
+        for (day, hours) in movebeam::history::heatmap(&periods, days) {
+            let mins: Vec<String> = hours
+                .iter()
+                .map(|d| (d.as_secs() / 60).to_string())
+                .collect();
+            writeln!(stdout, "{day}{sep}{}", mins.join(sep))?;
+        }
+    } else {
+        writeln!(stdout, "{date}{sep}{active}{sep}{longest}")?;
+        for (day, active, longest) in movebeam::history::daily_summary(&periods, days) {
+            writeln!(stdout, "{day}{sep}{}{sep}{}", total(active), total(longest))?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the progress of the configured goals, the current active period isn't included
+fn goals(config: &Config) -> Result<()> {
+    let fmt = Formatter::new(&config.format);
+    let config = &config.stats;
+    if config.goals.is_empty() {
+        println!("{}", tr!("goals-none"));
+        return Ok(());
+    }
+    let periods = movebeam::history::read(&movebeam::history_path()?)?;
+    for goal in &config.goals {
+        let progress =
+            movebeam::goals::evaluate(goal, &periods, config.break_length, Local::now(), &fmt)?;
+        println!("{progress}");
+    }
+    Ok(())
+}
+
+/// Removes the periods older than the given or configured number of days from the history
+fn purge_history(older_than: Option<u32>, all: bool) -> Result<()> {
+    let path = movebeam::history_path()?;
+    if all {
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {path:?}"))?;
+        }
+        println!("{}", tr!("history-removed"));
+        return Ok(());
+    }
+    let days = match older_than {
+        Some(days) => days,
+        None => Config::load_or_default(&movebeam::config_path()?)?
+            .stats
+            .retention
+            .context("No retention configured, pass --older-than or --all")?,
+    };
+    let before = SystemTime::now() - Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+    let removed = movebeam::history::prune(&path, before)?;
+    println!("{}", tr!("history-pruned", count = removed, days = days));
+    Ok(())
+}
+
+/// Prints the recent active periods in the format of a time tracker
+fn export_history(format: TrackerFormat, tag: &str, days: u32) -> Result<()> {
+    let periods = movebeam::history::read(&movebeam::history_path()?)?;
+    let periods = movebeam::history::recent(&periods, days);
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        TrackerFormat::Timew => {
+            let utc = |t: SystemTime| {
+                DateTime::<Utc>::from(t)
+                    .format("%Y%m%dT%H%M%SZ")
+                    .to_string()
+            };
+            let entries: Vec<_> = periods
+                .iter()
+                .map(|p| {
+                    serde_json::json!({
+                        "start": utc(p.start),
+                        "end": utc(p.end),
+                        "tags": [tag],
+                    })
+                })
+                .collect();
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&entries)?)?;
+        }
+        TrackerFormat::Watson => {
+            let local = |t: SystemTime| {
+                DateTime::<Local>::from(t)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            };
+            for period in periods {
+                writeln!(
+                    stdout,
+                    "watson add --from '{}' --to '{}' '{}'",
+                    local(period.start),
+                    local(period.end),
+                    tag.replace('\'', "'\\''")
                 )?;
             }
         }
@@ -76,9 +794,166 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn format_duration(d: Duration) -> String {
-    let secs = d.as_secs();
-    let m = secs / 60;
-    let s = secs % 60;
-    format!("{m:02}:{s:02}")
+/// Prints the service file of the init system, which runs the moved next to this executable
+fn service(init: InitSystem) -> Result<()> {
+    let moved = std::env::current_exe()
+        .context("Failed to find the executable")?
+        .with_file_name(movebeam::DAEMON_NAME);
+    let moved = moved.display();
+    match init {
+        InitSystem::Systemd => print!(
+            "\
+# ~/.config/systemd/user/moved.service, enable with `systemctl --user enable --now moved`
+[Unit]
+Description=Timer daemon of movebeam
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+ExecStart={moved}
+Restart=on-failure
+
+[Install]
+WantedBy=graphical-session.target
+"
+        ),
+        // supervise-daemon restarts moved and keeps it in the foreground
+        InitSystem::Openrc => print!(
+            "\
+#!/sbin/openrc-run
+# ~/.config/rc/init.d/moved, enable with `rc-update --user add moved default`
+description=\"Timer daemon of movebeam\"
+supervisor=supervise-daemon
+command=\"{moved}\"
+"
+        ),
+        InitSystem::Runit => print!(
+            "\
+#!/bin/sh
+# ~/.local/service/moved/run for a runsvdir of the user, has to be executable
+exec {moved} 2>&1
+"
+        ),
+        InitSystem::S6 => print!(
+            "\
+#!/bin/execlineb -P
+# moved/run in the scan directory of an s6-svscan of the user, has to be executable
+# For s6-rc, also add a file `type` containing `longrun` to the directory
+fdmove -c 2 1
+{moved}
+"
+        ),
+    }
+    Ok(())
+}
+
+/// Checks the setup and prints the results with remediation hints
+fn doctor() -> Result<ExitCode> {
+    let mut stdout = std::io::stdout().lock();
+    let mut healthy = true;
+    let mut report = |name: &str, result: Result<String>, hint: String| -> Result<()> {
+        match result {
+            Ok(details) => writeln!(stdout, "[ OK ] {name}: {details}"),
+            Err(e) => {
+                healthy = false;
+                writeln!(stdout, "[FAIL] {name}: {e}")?;
+                writeln!(stdout, "       {}", tr!("doctor-hint", hint = hint))
+            }
+        }?;
+        Ok(())
+    };
+
+    let config = movebeam::config_path().and_then(|path| {
+        Config::load_or_default(&path).with_context(|| format!("Invalid config {path:?}"))
+    });
+    report(
+        &tr!("doctor-config"),
+        config
+            .as_ref()
+            .map(|c| tr!("doctor-config-ok", count = c.timers.len()))
+            .map_err(|e| anyhow!("{e:#}")),
+        tr!("doctor-config-hint"),
+    )?;
+
+    report(
+        "moved",
+        movebeam::daemon_socket()
+            .and_then(SocketClient::connect)
+            .and_then(|mut client| {
+                let resp = client.send(&Message::List(ListQuery::default()).encode()?)?;
+                match Response::decode(&resp)? {
+                    Response::List(list) => Ok(tr!("doctor-moved-ok", count = list.len())),
+                    _ => Err(anyhow!(tr!("doctor-unexpected-response"))),
+                }
+            }),
+        tr!("doctor-moved-hint"),
+    )?;
+
+    let uses_actived = config.is_ok_and(|c| {
+        c.activity
+            .is_some_and(|a| a.source.contains(&ActivitySource::Evdev))
+    });
+    if uses_actived {
+        let socket = movebeam::activity_daemon_socket();
+        report(
+            "actived",
+            SocketClient::connect(socket.clone()).and_then(|mut client| {
+                let query = ActivityQuery {
+                    detail: Privacy::Timestamps,
+                };
+                let info = ActivityInfo::decode(&client.send(&query.encode()?)?)?;
+                Ok(tr!(
+                    "doctor-actived-ok",
+                    time = format_duration(info.input_elapsed)
+                ))
+            }),
+            tr!("doctor-actived-hint"),
+        )?;
+        report(
+            &tr!("doctor-socket"),
+            fs::metadata(&socket)
+                .with_context(|| tr!("doctor-socket-missing", socket = format!("{socket:?}")))
+                .and_then(|m| {
+                    let mode = m.permissions().mode() & 0o777;
+                    let path = CString::new(socket.as_os_str().as_bytes())?;
+                    // SAFETY: the path is a valid C string
+                    if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+                        Ok(format!("{mode:o}"))
+                    } else {
+                        Err(anyhow!(tr!(
+                            "doctor-socket-unwritable",
+                            socket = format!("{socket:?}"),
+                            mode = format!("{mode:o}")
+                        )))
+                    }
+                }),
+            tr!("doctor-socket-hint"),
+        )?;
+    }
+
+    report(
+        &tr!("doctor-display"),
+        if let Ok(display) = std::env::var("WAYLAND_DISPLAY") {
+            Ok(format!("Wayland ({display})"))
+        } else if let Ok(display) = std::env::var("DISPLAY") {
+            Ok(format!("X11 ({display})"))
+        } else {
+            Err(anyhow!(tr!("doctor-display-none")))
+        },
+        tr!("doctor-display-hint"),
+    )?;
+
+    report(
+        &tr!("doctor-notifications"),
+        notify_rust::get_server_information()
+            .map(|info| format!("{} {}", info.name, info.version))
+            .map_err(|e| anyhow!("{e}")),
+        tr!("doctor-notifications-hint"),
+    )?;
+
+    Ok(if healthy {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
 }
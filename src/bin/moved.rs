@@ -1,24 +1,17 @@
-use anyhow::Result;
-use clap::{command, Parser};
+use anyhow::{Context, Result};
+use clap::Parser;
 use movebeam::{
-    config::{Config, TimerConfig},
-    msg::{Encoding, Message, Response, ResponseError, TimerInfo},
-    socket::{SocketClient, SocketServer},
+    config::{parse_mmss, Config},
+    daemon::{mock_config, Daemon, Simulation},
 };
-use parking_lot::Mutex;
 use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    os::fd::{AsRawFd, FromRawFd},
     path::PathBuf,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    thread,
-    time::{Duration, Instant, SystemTime},
+    time::Duration,
 };
-use tracing::{error, info, trace};
-use tracing_subscriber::{filter::EnvFilter, fmt, prelude::*};
-
-const HEARTBEAT: Duration = Duration::from_secs(1);
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -26,212 +19,134 @@ pub struct Args {
     /// Path of configuration file
     #[arg(short, long)]
     pub config: Option<PathBuf>,
+    /// Simulate the clock at a multiplied speed, e.g. `speed=60`
+    #[arg(long, hide = true, value_parser = parse_simulate)]
+    pub simulate: Option<f64>,
+    /// Replay input events from a trace recorded by actived, implies simulation
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+    /// Listen to the input devices in-process instead of using actived, which needs read
+    /// access to `/dev/input`
+    #[arg(long)]
+    pub with_activity: bool,
+    /// Serve fixed timers with frozen clocks instead of the configured ones, to test clients
+    ///
+    /// The idle time cycles through 00:00, 00:45 and 00:05, each for 20 seconds.
+    #[arg(long, conflicts_with_all = ["config", "simulate", "replay"])]
+    pub mock: bool,
+    /// Fork into the background once the socket is ready and log to a file, for init systems
+    /// and scripts that expect a daemon to detach
+    ///
+    /// The process ID is written to `moved.pid` in the runtime directory and the log goes to
+    /// `moved.log` in the state directory unless a log file is configured.
+    #[arg(long, conflicts_with = "foreground")]
+    pub daemonize: bool,
+    /// Stay in the foreground and log to stderr, the default
+    #[arg(long)]
+    pub foreground: bool,
+    /// Time after a reset in which `movebeam undo` restores the timers (mm:ss)
+    #[arg(long, value_parser = parse_mmss, default_value = "00:30")]
+    pub undo_window: Duration,
 }
 
-fn main() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::builder().from_env().unwrap())
-        .init();
-    let args = Args::parse();
-
-    Daemon::start(args)?.run()
-}
-
-struct TimerState {
-    clock: Duration,
-    went_off: bool,
-    config: TimerConfig,
+fn parse_simulate(s: &str) -> Result<f64, String> {
+    s.strip_prefix("speed=")
+        .ok_or_else(|| "expected 'speed=<multiplier>'".to_string())?
+        .parse::<f64>()
+        .map_err(|e| format!("invalid speed: {e}"))
 }
 
-struct State {
-    config: Config,
-    activity_daemon_client: Option<SocketClient>,
-    timers: Vec<TimerState>,
-    last_update: Instant,
-}
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut config = if args.mock {
+        mock_config()
+    } else {
+        let config_path = args.config.clone().unwrap_or(movebeam::config_path()?);
+        Config::load_or_default(&config_path)?
+    };
+    // Forking has to happen before the logging and the daemon start their threads
+    let ready = if args.daemonize {
+        if config.logging.file.is_none() {
+            config.logging.file = Some(movebeam::log_path()?);
+        }
+        Some(daemonize()?)
+    } else {
+        None
+    };
+    let _log_guard = movebeam::logging::init(&config.logging)?;
+
+    let simulation = match (args.simulate, &args.replay) {
+        _ if args.mock => Some(Simulation::mock()),
+        (None, None) => None,
+        (speed, replay) => {
+            let replay = match replay {
+                Some(path) => movebeam::recording::read_trace(path)?
+                    .into_iter()
+                    .map(|(offset, _)| offset)
+                    .collect(),
+                None => VecDeque::new(),
+            };
+            Some(Simulation::new(speed.unwrap_or(1.0), replay))
+        }
+    };
 
-impl State {
-    fn init(config: Config) -> Result<Self> {
-        let timers: Vec<TimerState> = config
-            .timers
-            .iter()
-            .map(|t| TimerState {
-                clock: Duration::ZERO,
-                went_off: false,
-                config: t.clone(),
-            })
-            .collect();
-        let activity_daemon_client = if config.activity.is_some() {
-            Some(SocketClient::connect(movebeam::activity_daemon_socket())?)
-        } else {
-            None
-        };
-        Ok(Self {
-            config,
-            activity_daemon_client,
-            timers,
-            last_update: Instant::now(),
-        })
+    let mut daemon = Daemon::start(config, simulation, args.with_activity, args.undo_window)?;
+    if let Some(ready) = ready {
+        detach(ready)?;
     }
+    daemon.run()
 }
 
-struct Daemon {
-    state: Arc<Mutex<State>>,
-    shutdown: Arc<AtomicBool>,
-}
-
-impl Daemon {
-    fn start(args: Args) -> Result<Self> {
-        let shutdown = Arc::new(AtomicBool::new(false));
-        signal_hook::flag::register(signal_hook::consts::SIGINT, shutdown.clone())?;
-
-        let config_path = args.config.unwrap_or(movebeam::config_path()?);
-        let config = Config::load_or_default(&config_path)?;
-        let state = Arc::new(Mutex::new(State::init(config)?));
-
-        let socket = SocketServer::create(movebeam::daemon_socket(), false)?;
-        Self::start_socket(socket, shutdown.clone(), state.clone());
-
-        Ok(Self { shutdown, state })
-    }
-
-    fn run(&mut self) -> Result<()> {
-        while !self.shutdown.load(Ordering::Relaxed) {
-            {
-                let mut state = self.state.lock();
-                Self::update(&mut state)?;
-            }
-            thread::sleep(HEARTBEAT);
-        }
-        Ok(())
-    }
-
-    fn start_socket(mut socket: SocketServer, shutdown: Arc<AtomicBool>, state: Arc<Mutex<State>>) {
-        thread::spawn(move || {
-            socket
-                .serve_until(shutdown, |msg| {
-                    match Self::handle_connection(state.clone(), msg) {
-                        Ok(msg) => Some(msg),
-                        Err(e) => {
-                            error!("Failed to handle connection: {e}");
-                            None
-                        }
-                    }
-                })
-                .unwrap();
-        });
+/// Forks a child in a new session, the parent exits once the child reports that it's ready
+///
+/// Returns the pipe the child reports on. The child keeps stderr until then, so errors during
+/// the start still reach the terminal.
+fn daemonize() -> Result<File> {
+    let mut fds = [0; 2];
+    // SAFETY: the array has room for the two descriptors
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error()).context("Failed to create pipe");
     }
-
-    fn update(state: &mut State) -> Result<()> {
-        let input_elapsed = if let Some(client) = &mut state.activity_daemon_client {
-            let resp = client.send(&[1])?;
-            Some(SystemTime::decode(&resp)?.elapsed()?)
-        } else {
-            None
-        };
-
-        let mut reset = false;
-        let delta = state.last_update.elapsed();
-
-        let (inactivity_pause, inactivity_reset) = if let Some(activity) = &state.config.activity {
-            (activity.inactivity_pause, activity.inactivity_reset)
-        } else {
-            (None, None)
-        };
-
-        // Reset when inactive
-        // Also checks for the delta to be bigger which can happen when pc was in sleep
-        if inactivity_reset.is_some()
-            && (input_elapsed >= inactivity_reset || Some(delta) >= inactivity_reset)
-        {
-            reset = true;
-        }
-
-        for timer in state.timers.iter_mut() {
-            trace!(
-                "Update {}, clock: {:?}, interval: {:?}",
-                timer.config.name,
-                timer.clock,
-                timer.config.interval
-            );
-            if timer.config.duration.is_some() && input_elapsed > timer.config.duration {
-                // Rest if over break duration
-                timer.clock = Duration::ZERO;
-                reset = true;
-            }
-
-            if reset {
-                info!("Reset timer {}", timer.config.name);
-                timer.clock = Duration::ZERO;
-                continue;
+    // SAFETY: the descriptors were just created and are owned by nothing else
+    let (mut read, write) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    // SAFETY: no other threads exist yet, so the child can continue normally
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()).context("Failed to fork"),
+        0 => {
+            drop(read);
+            // SAFETY: plain system call, fails only if the process already leads a group
+            if unsafe { libc::setsid() } == -1 {
+                return Err(io::Error::last_os_error()).context("Failed to create session");
             }
-
-            if input_elapsed <= inactivity_pause {
-                // Only update clock if not paused
-                timer.clock += delta;
-            }
-
-            if !timer.went_off && timer.clock > timer.config.interval {
-                info!("Timer {} went off", timer.config.name);
-                if timer.config.notify {
-                    movebeam::send_notification(
-                        format!("Timer {} went off", timer.config.name),
-                        "Time to take a break!".to_string(),
-                    )
-                }
-                timer.went_off = true;
+            let null = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/null")?;
+            // SAFETY: both descriptors are valid
+            unsafe {
+                libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+                libc::dup2(null.as_raw_fd(), libc::STDOUT_FILENO);
             }
+            Ok(write)
+        }
+        _ => {
+            drop(write);
+            // The pipe closes without a byte when the child fails, after printing why
+            let mut ready = [0];
+            let code = match read.read(&mut ready) {
+                Ok(1) => 0,
+                _ => 1,
+            };
+            std::process::exit(code)
         }
-        state.last_update = Instant::now();
-        Ok(())
     }
+}
 
-    fn handle_connection(state: Arc<Mutex<State>>, msg: &[u8]) -> Result<Vec<u8>> {
-        let command = Message::decode(msg)?;
-        let mut state = state.lock();
-        let response = match command {
-            Message::List => Response::List(
-                state
-                    .timers
-                    .iter()
-                    .map(|t| {
-                        (
-                            t.config.name.clone(),
-                            TimerInfo {
-                                elapsed: t.clock,
-                                interval: t.config.interval,
-                            },
-                        )
-                    })
-                    .collect(),
-            ),
-            Message::Get(name) => state
-                .timers
-                .iter()
-                .find(|t| t.config.name == name)
-                .map(|t| {
-                    Response::Timer(TimerInfo {
-                        elapsed: t.clock,
-                        interval: t.config.interval,
-                    })
-                })
-                .unwrap_or(Response::Error(ResponseError::NotFound)),
-            Message::Reset(name) => {
-                if let Some(timer) = state.timers.iter_mut().find(|t| t.config.name == name) {
-                    timer.clock = Duration::ZERO;
-                    Response::Ok
-                } else {
-                    Response::Error(ResponseError::NotFound)
-                }
-            }
-            Message::ResetAll => {
-                for timer in state.timers.iter_mut() {
-                    timer.clock = Duration::ZERO;
-                }
-                Response::Ok
-            }
-        };
-        response.encode()
-    }
+/// Lets the waiting parent exit and releases the terminal and the working directory
+fn detach(mut ready: File) -> Result<()> {
+    std::env::set_current_dir("/")?;
+    let null = OpenOptions::new().write(true).open("/dev/null")?;
+    // SAFETY: both descriptors are valid
+    unsafe { libc::dup2(null.as_raw_fd(), libc::STDERR_FILENO) };
+    ready.write_all(&[1]).context("Failed to report readiness")
 }
@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState, Region},
+    delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm,
+    output::{OutputHandler, OutputState},
+    registry::{ProvidesRegistryState, RegistryState},
+    registry_handlers,
+    shell::{
+        wlr_layer::{
+            Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+            LayerSurfaceConfigure,
+        },
+        WaylandSurface,
+    },
+    shm::{slot::SlotPool, Shm, ShmHandler},
+};
+use std::{thread, time::Duration};
+use tracing::{error, info};
+use wayland_client::{
+    globals::registry_queue_init,
+    protocol::{wl_output, wl_shm, wl_surface},
+    Connection, EventQueue, QueueHandle,
+};
+
+/// Width of the border in pixels
+const WIDTH: u32 = 12;
+/// Opacity of the border at full intensity
+const MAX_ALPHA: f32 = 0.6;
+/// Interval at which the Wayland events are processed
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Tinted edge around the screen drawn on a Wayland layer-shell surface, it doesn't take input
+pub struct Border {
+    sender: Sender<f32>,
+    intensity: f32,
+}
+
+impl Border {
+    /// Connects to the compositor and draws the border in a new thread
+    pub fn spawn() -> Result<Self> {
+        let conn = Connection::connect_to_env().context("Failed to connect to Wayland")?;
+        let (globals, queue) = registry_queue_init(&conn)?;
+        let qh = queue.handle();
+        let compositor = CompositorState::bind(&globals, &qh)?;
+        let layer_shell = LayerShell::bind(&globals, &qh).context("No layer shell available")?;
+        let shm = Shm::bind(&globals, &qh)?;
+
+        let surface = compositor.create_surface(&qh);
+        // An empty input region lets all clicks pass through
+        let region = Region::new(&compositor)?;
+        surface.set_input_region(Some(region.wl_region()));
+        let layer = layer_shell.create_layer_surface(
+            &qh,
+            surface,
+            Layer::Overlay,
+            Some("movebeam-border"),
+            None,
+        );
+        layer.set_anchor(Anchor::all());
+        layer.set_exclusive_zone(-1);
+        layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+        layer.set_size(0, 0);
+        layer.commit();
+
+        let state = BorderState {
+            registry: RegistryState::new(&globals),
+            output: OutputState::new(&globals, &qh),
+            pool: SlotPool::new(WIDTH as usize * 4, &shm)?,
+            shm,
+            layer,
+            size: None,
+            intensity: 0.0,
+            closed: false,
+        };
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        thread::spawn(move || {
+            if let Err(e) = run(queue, state, receiver) {
+                error!("Border failed: {e:#}");
+            }
+        });
+        info!("Started overdue border");
+        Ok(Self {
+            sender,
+            intensity: 0.0,
+        })
+    }
+
+    /// Sets the intensity between 0 (hidden) and 1
+    pub fn set_intensity(&mut self, intensity: f32) {
+        // Only redraw on visible changes
+        let intensity = (intensity.clamp(0.0, 1.0) * 32.0).round() / 32.0;
+        if intensity != self.intensity {
+            self.intensity = intensity;
+            let _ = self.sender.send(intensity);
+        }
+    }
+}
+
+fn run(
+    mut queue: EventQueue<BorderState>,
+    mut state: BorderState,
+    receiver: Receiver<f32>,
+) -> Result<()> {
+    while !state.closed {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(intensity) => {
+                state.intensity = intensity;
+                state.draw()?;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // The daemon stopped
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        queue.roundtrip(&mut state)?;
+    }
+    Ok(())
+}
+
+struct BorderState {
+    registry: RegistryState,
+    output: OutputState,
+    shm: Shm,
+    pool: SlotPool,
+    layer: LayerSurface,
+    /// Size given by the compositor, the surface is drawn once it is known
+    size: Option<(u32, u32)>,
+    intensity: f32,
+    closed: bool,
+}
+
+impl BorderState {
+    fn draw(&mut self) -> Result<()> {
+        let Some((width, height)) = self.size else {
+            return Ok(());
+        };
+        let (buffer, canvas) = self.pool.create_buffer(
+            width as i32,
+            height as i32,
+            width as i32 * 4,
+            wl_shm::Format::Argb8888,
+        )?;
+        let alpha = (self.intensity * MAX_ALPHA * 255.0) as u32;
+        // Premultiplied red
+        let color = (alpha << 24) | (alpha << 16);
+        for (i, pixel) in canvas.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let edge = x < WIDTH || y < WIDTH || x >= width - WIDTH || y >= height - WIDTH;
+            let value: u32 = if edge { color } else { 0 };
+            pixel.copy_from_slice(&value.to_le_bytes());
+        }
+        let surface = self.layer.wl_surface();
+        surface.damage_buffer(0, 0, width as i32, height as i32);
+        buffer.attach_to(surface)?;
+        self.layer.commit();
+        Ok(())
+    }
+}
+
+impl LayerShellHandler for BorderState {
+    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface) {
+        self.closed = true;
+    }
+
+    fn configure(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _: u32,
+    ) {
+        let (width, height) = configure.new_size;
+        if width > 2 * WIDTH && height > 2 * WIDTH && self.size != Some((width, height)) {
+            self.size = Some((width, height));
+            if let Err(e) = self.draw() {
+                error!("Failed to draw border: {e:#}");
+            }
+        }
+    }
+}
+
+impl CompositorHandler for BorderState {
+    fn scale_factor_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: i32,
+    ) {
+    }
+
+    fn transform_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) {}
+
+    fn surface_enter(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for BorderState {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output
+    }
+
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+}
+
+impl ShmHandler for BorderState {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm
+    }
+}
+
+impl ProvidesRegistryState for BorderState {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry
+    }
+    registry_handlers![OutputState];
+}
+
+delegate_compositor!(BorderState);
+delegate_output!(BorderState);
+delegate_shm!(BorderState);
+delegate_layer!(BorderState);
+delegate_registry!(BorderState);
@@ -0,0 +1,127 @@
+use crate::activity::Polled;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Time between checks of the calendar
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Periods in which notifications are held back, e.g. during meetings
+pub struct QuietPeriods {
+    calendar: Option<PathBuf>,
+    /// Result of the quiet command, which runs in the background as it may take a while
+    command: Option<Polled<bool>>,
+    last_check: Option<Instant>,
+    quiet: bool,
+}
+
+impl QuietPeriods {
+    pub fn new(calendar: Option<PathBuf>, command: Option<String>) -> Option<Self> {
+        if calendar.is_none() && command.is_none() {
+            return None;
+        }
+        Some(Self {
+            calendar,
+            command: command.map(|command| {
+                Polled::start(CHECK_INTERVAL, false, move || Ok(quiet_command(&command)))
+            }),
+            last_check: None,
+            quiet: false,
+        })
+    }
+
+    /// Returns whether notifications should be held back right now
+    pub fn active(&mut self) -> bool {
+        if self
+            .last_check
+            .is_some_and(|t| t.elapsed() < CHECK_INTERVAL)
+        {
+            return self.quiet;
+        }
+        self.last_check = Some(Instant::now());
+        let mut quiet = false;
+        if let Some(path) = &self.calendar {
+            match in_event(path, Local::now()) {
+                Ok(busy) => quiet |= busy,
+                Err(e) => warn!("Failed to check calendar: {e:#}"),
+            }
+        }
+        if let Some(command) = &self.command {
+            quiet |= command.latest().unwrap_or_default();
+        }
+        if quiet != self.quiet {
+            if quiet {
+                info!("Quiet period started, holding back notifications");
+            } else {
+                info!("Quiet period ended");
+            }
+        }
+        self.quiet = quiet;
+        quiet
+    }
+}
+
+/// Runs the quiet command, which succeeds during a quiet period
+fn quiet_command(command: &str) -> bool {
+    match Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+    {
+        Ok(status) => status.success(),
+        Err(e) => {
+            warn!("Failed to run quiet command: {e}");
+            false
+        }
+    }
+}
+
+/// Checks whether an event of an ICS calendar is taking place
+///
+/// All-day and recurring events are ignored.
+pub fn in_event(path: &Path, now: DateTime<Local>) -> Result<bool> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read calendar {path:?}"))?;
+    // Unfold continuation lines
+    let content = content.replace("\r\n ", "").replace("\n ", "");
+    let (mut start, mut end, mut recurring) = (None, None, false);
+    for line in content.lines().map(str::trim_end) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = key.split(';').next().unwrap_or_default();
+        match name {
+            "BEGIN" if value == "VEVENT" => (start, end, recurring) = (None, None, false),
+            "DTSTART" => start = parse_datetime(value),
+            "DTEND" => end = parse_datetime(value),
+            "RRULE" => recurring = true,
+            "END" if value == "VEVENT" => {
+                if let (Some(start), Some(end), false) = (start, end, recurring) {
+                    if start <= now && now < end {
+                        return Ok(true);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(false)
+}
+
+/// Parses a date-time in UTC (`20240101T120000Z`) or local time (`20240101T120000`)
+fn parse_datetime(value: &str) -> Option<DateTime<Local>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        Some(Utc.from_utc_datetime(&time).with_timezone(&Local))
+    } else {
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        Local.from_local_datetime(&time).earliest()
+    }
+}
@@ -1,21 +1,206 @@
-use clap::{command, Parser};
+use crate::{
+    config::{parse_days, parse_mmss},
+    format::ColorChoice,
+    msg::SortKey,
+};
+use clap::Parser;
+use std::{path::PathBuf, time::Duration};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub cmd: CliCommand,
+    /// Show the time until the timer goes off instead of the elapsed time
+    #[arg(long, global = true)]
+    pub remaining: bool,
+    /// When to use colors
+    #[arg(long, global = true, default_value = "auto")]
+    pub color: ColorChoice,
+    /// Only output plain ASCII characters without colors
+    #[arg(long, global = true)]
+    pub ascii: bool,
+    /// Use the stable machine-readable output format
+    #[arg(long, global = true)]
+    pub porcelain: bool,
+    /// Output comma-separated values with a header row
+    #[arg(long, global = true, conflicts_with = "porcelain")]
+    pub csv: bool,
+    /// Query a daemon on the network instead, e.g. `desktop:7878`
+    #[arg(long, global = true, requires = "token")]
+    pub remote: Option<String>,
+    /// Connect to the control socket, needed for changes when moved is configured read-only
+    #[arg(
+        long,
+        global = true,
+        env = "MOVEBEAM_CONTROL",
+        conflicts_with = "remote"
+    )]
+    pub control: bool,
+    /// Token of the remote daemon
+    #[arg(long, global = true, env = "MOVEBEAM_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
 }
 
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum CliCommand {
     /// List of information from all timers
-    List,
+    List {
+        /// Only list timers with this tag
+        #[clap(short, long)]
+        tag: Option<String>,
+        /// Order of the listed timers
+        #[clap(short, long)]
+        sort: Option<SortKey>,
+        /// Only list timers that are overdue
+        #[clap(short, long)]
+        overdue: bool,
+        /// Only list timers that are currently running
+        #[clap(short, long)]
+        running: bool,
+        /// End with the timer that is due next, e.g. `next: move in 03:12`
+        #[clap(long)]
+        summary: bool,
+    },
     /// Get the information of a specific timer
-    Get { name: String },
+    Get {
+        name: String,
+        /// Also show the effective configuration and where it came from
+        #[clap(short, long)]
+        verbose: bool,
+    },
     /// Status bar
     Bar {
         name: String,
+        /// Style of a `[bar.styles.<name>]` section in the configuration, overridden by the
+        /// other options
+        #[clap(long)]
+        style: Option<String>,
+        /// Number of characters [default: 16]
+        #[clap(short, long)]
+        size: Option<usize>,
+        /// [default: █]
+        #[clap(short, long)]
+        fill: Option<String>,
+        /// [default: ░]
+        #[clap(short, long)]
+        empty: Option<String>,
+        /// [default: ▕]
+        #[clap(short, long)]
+        left: Option<String>,
+        /// [default: ▏]
+        #[clap(short, long)]
+        right: Option<String>,
+        #[clap(short, long)]
+        blink: bool,
+        /// Text in front of the bar, e.g. the name of the timer
+        #[clap(long)]
+        label: Option<String>,
+        /// Show the remaining time behind the bar
+        #[clap(short = 't', long)]
+        show_time: bool,
+        /// Keep printing the bar, right away when a timer changes and otherwise every second
+        #[clap(long)]
+        follow: bool,
+    },
+    /// Reset a specific timer
+    Reset {
+        #[clap(required_unless_present = "tag", conflicts_with = "tag")]
+        name: Option<String>,
+        /// Reset all timers with this tag
+        #[clap(short, long)]
+        tag: Option<String>,
+        /// Only show the timers that would be reset
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Reset all timers
+    ResetAll {
+        /// Timers to leave untouched
+        #[clap(long)]
+        except: Vec<String>,
+        /// Only show the timers that would be reset
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Restore the timers of the last reset, possible within the undo window of moved
+    Undo,
+    /// Start a break now, the timer resets once the break is complete
+    Break {
+        name: String,
+        /// Break duration (mm:ss), defaults to the duration of the timer
+        #[clap(value_parser = parse_mmss)]
+        duration: Option<Duration>,
+    },
+    /// Postpone a timer that went off, it goes off again after the given time
+    Snooze {
+        name: String,
+        /// Time until the timer goes off again (mm:ss)
+        #[clap(value_parser = parse_mmss, default_value = "05:00")]
+        duration: Duration,
+    },
+    /// Rename a timer, which is kept across restarts
+    Rename { name: String, new_name: String },
+    /// Block until a specific timer goes off
+    Wait {
+        name: String,
+        /// Give up after this time (mm:ss)
+        #[clap(short, long, value_parser = parse_mmss)]
+        timeout: Option<Duration>,
+    },
+    /// Start a one-shot countdown which notifies once it expires
+    Countdown {
+        name: String,
+        /// Duration of the countdown (mm:ss)
+        #[clap(value_parser = parse_mmss)]
+        duration: Duration,
+    },
+    /// Show the version and capabilities of the daemon
+    Status,
+    /// Control the daemon
+    Daemon {
+        #[command(subcommand)]
+        cmd: DaemonCommand,
+    },
+    /// Export or import the timer clocks
+    State {
+        #[command(subcommand)]
+        cmd: StateCommand,
+    },
+    /// Show the recorded activity of the last days
+    Stats {
+        #[command(subcommand)]
+        cmd: Option<StatsCommand>,
+        /// Show the active minutes per hour of the day
+        #[arg(long)]
+        heatmap: bool,
+        /// Print the active periods as entries of a time tracker
+        #[arg(long, value_enum, conflicts_with = "heatmap")]
+        export: Option<TrackerFormat>,
+        /// Tag or project of the exported entries
+        #[arg(long, default_value = "movebeam")]
+        tag: String,
+        /// Number of days to show
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// Show the progress of the goals in the configuration
+    Goals,
+    /// Check the setup for common problems
+    Doctor,
+    /// Print a service file that starts moved, with where to install it
+    Service {
+        /// Init system or supervisor of the service
+        #[arg(long, value_enum, default_value = "systemd")]
+        init: InitSystem,
+    },
+    /// Print the time since the last input in milliseconds, like xprintidle
+    Idle,
+    /// Status bar of the time since the last input, filled once the threshold is reached
+    IdleBar {
+        /// Time without input at which the bar is full (mm:ss)
+        #[clap(short, long, value_parser = parse_mmss, default_value = "05:00")]
+        threshold: Duration,
         #[clap(short, long, default_value_t = 16)]
         size: usize,
         #[clap(short, long, default_value_t = String::from("█"))]
@@ -26,11 +211,57 @@ pub enum CliCommand {
         left: String,
         #[clap(short, long, default_value_t = String::from("▏"))]
         right: String,
-        #[clap(short, long)]
-        blink: bool,
     },
-    /// Reset a specific timer
-    Reset { name: String },
-    /// Reset all timers
-    ResetAll,
+    /// Inject user input into a simulating daemon
+    #[command(hide = true)]
+    Input,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum DaemonCommand {
+    /// Save the state and stop the daemon
+    Stop,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum StatsCommand {
+    /// Remove old periods from the activity history
+    Purge {
+        /// Keep the given number of days instead of the configured retention, e.g. `30d`
+        #[arg(long, value_parser = parse_days)]
+        older_than: Option<u32>,
+        /// Remove the whole history
+        #[arg(long, conflicts_with = "older_than")]
+        all: bool,
+    },
+}
+
+/// Time trackers the activity history can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TrackerFormat {
+    /// JSON for `timew import`
+    Timew,
+    /// Shell commands of `watson add`
+    Watson,
+}
+
+/// Init systems and supervisors moved can run under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InitSystem {
+    /// User unit of systemd
+    Systemd,
+    /// Service script for the user services of OpenRC
+    Openrc,
+    /// Run script of a runit service directory
+    Runit,
+    /// Run script of an s6 service directory
+    S6,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum StateCommand {
+    /// Write the timer clocks as JSON to stdout
+    Export,
+    /// Restore the timer clocks from an exported JSON file
+    Import { file: PathBuf },
 }
@@ -1,12 +1,87 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::Deserialize;
-use std::{fs, path::Path, time::Duration};
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tracing::info;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimerType {
+    /// Counts the time since the last break
+    #[default]
+    Break,
+    /// Counts the active time of the whole day
+    Daily,
+    /// Counts the time of the day spent in matching applications
+    App,
+    /// Goes off at fixed times of the day, counting the time since the previous one
+    Reminder,
+    /// Counts down once and is removed afterwards, only created at runtime
+    #[serde(skip)]
+    Countdown,
+}
+
+/// How audio playback affects a timer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioPolicy {
+    /// Playing audio counts as activity
+    Activity,
+    /// Playing audio only prevents the timer from being reset
+    PreventReset,
+}
+
+/// Which time a timer counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CountMode {
+    /// All time until the inactivity pause
+    #[default]
+    WallClock,
+    /// Only the seconds with input
+    ActiveOnly,
+}
+
+/// Events that may reset a timer, scheduled resets always apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetSource {
+    /// Both the user and inactivity
+    #[default]
+    Any,
+    /// Only the user, e.g. `movebeam reset`
+    Manual,
+    /// Only inactivity
+    Inactivity,
+    /// Neither, e.g. for a timer counting the total time at the computer today
+    Never,
+}
+
+impl ResetSource {
+    pub fn manual(self) -> bool {
+        matches!(self, ResetSource::Any | ResetSource::Manual)
+    }
+
+    pub fn inactivity(self) -> bool {
+        matches!(self, ResetSource::Any | ResetSource::Inactivity)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct TimerConfig {
     pub name: String,
-    #[serde(with = "mmss_format")]
+    /// Template the fields were filled in from
+    #[serde(default)]
+    pub extends: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: TimerType,
+    /// Not used by reminders, which go off at their times of the day instead
+    #[serde(default, with = "mmss_format")]
     pub interval: Duration,
     #[serde(
         default,
@@ -20,12 +95,271 @@ pub struct TimerConfig {
         skip_serializing_if = "Option::is_none"
     )]
     pub duration: Option<Duration>,
+    /// Time after the interval before the timer counts as overdue
+    #[serde(
+        default,
+        with = "mmss_format_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub grace: Option<Duration>,
     #[serde(default)]
     pub notify: bool,
+    /// Longest time the notification is held back while the user is in flow
+    #[serde(
+        default,
+        with = "mmss_format_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_defer: Option<Duration>,
+    /// Scale the clock speed with the input intensity reported by the activity daemon
+    #[serde(default)]
+    pub adaptive: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Exercise suggestions shown in turn in the notifications
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+    /// File with additional suggestions, one per line
+    #[serde(default)]
+    pub suggestions_file: Option<PathBuf>,
+    /// Local time (hh:mm) at which the clock is zeroed every day, daily timers start a new
+    /// day at 04:00 by default
+    #[serde(
+        default,
+        with = "hhmm_format_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reset_at: Option<Duration>,
+    /// Local times of the day (hh:mm) at which a reminder goes off
+    #[serde(default, with = "hhmm_format_vec")]
+    pub at: Vec<Duration>,
+    /// Additional points at which a daily timer notifies
+    #[serde(default, with = "mmss_format_vec")]
+    pub thresholds: Vec<Duration>,
+    /// Time after the interval at which a swaynag or i3-nagbar with quick actions is shown
+    #[serde(
+        default,
+        with = "mmss_format_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub nag_after: Option<Duration>,
+    /// Count a pause that ends shortly before the timer goes off as its break
+    #[serde(default)]
+    pub credit: Option<BreakCredit>,
+    /// Announce the timer going off and the end of breaks through speech-dispatcher
+    #[serde(default)]
+    pub speak: bool,
+    /// Tint the screen edges while the timer is overdue, more strongly over time (Wayland only)
+    #[serde(default)]
+    pub border: bool,
+    /// LED in `/sys/class/leds` that pulses while the timer is overdue
+    #[serde(default)]
+    pub led: Option<String>,
+    #[serde(default)]
+    pub count: CountMode,
+    /// Events that may reset the timer
+    #[serde(default)]
+    pub reset_by: ResetSource,
+    /// Consider audio playback as presence
+    #[serde(default)]
+    pub audio: Option<AudioPolicy>,
+    /// Regex matched against the class and title of the focused window, used by app timers
+    #[serde(default, rename = "match")]
+    pub pattern: Option<String>,
+    /// Application classes in which the clock is paused, e.g. a video player
+    #[serde(default)]
+    pub pause_in: Vec<String>,
+    /// Application classes in which the clock keeps running even without input
+    #[serde(default)]
+    pub count_in: Vec<String>,
+}
+
+/// Pause that resets a timer which is about to go off, so that it doesn't go off right after
+/// e.g. lunch
+#[derive(Debug, Clone, Deserialize)]
+pub struct BreakCredit {
+    /// Shortest time without input that counts
+    #[serde(with = "mmss_format")]
+    pub min_break: Duration,
+    /// Time before the timer goes off in which the pause has to end, overdue timers are reset
+    /// by it as well
+    #[serde(with = "mmss_format")]
+    pub within: Duration,
+}
+
+impl TimerConfig {
+    /// Lists the effective settings as name and value, leaving out the unset ones
+    pub fn describe(&self) -> Vec<(String, String)> {
+        let mmss = |d: &Duration| crate::format::format_duration(*d);
+        let hhmm = |t: &Duration| format!("{:02}:{:02}", t.as_secs() / 3600, t.as_secs() / 60 % 60);
+        let mut settings = vec![
+            (
+                "type".to_string(),
+                format!("{:?}", self.kind).to_lowercase(),
+            ),
+            ("interval".to_string(), mmss(&self.interval)),
+        ];
+        let mut add = |name: &str, value: Option<String>| {
+            if let Some(value) = value {
+                settings.push((name.to_string(), value));
+            }
+        };
+        add("suggested", self.suggested.as_ref().map(mmss));
+        add("duration", self.duration.as_ref().map(mmss));
+        add("grace", self.grace.as_ref().map(mmss));
+        add("notify", Some(self.notify.to_string()));
+        add("max_defer", self.max_defer.as_ref().map(mmss));
+        add("adaptive", self.adaptive.then(|| "true".to_string()));
+        add(
+            "count",
+            (self.count == CountMode::ActiveOnly).then(|| "active_only".to_string()),
+        );
+        add(
+            "reset_by",
+            (self.reset_by != ResetSource::Any)
+                .then(|| format!("{:?}", self.reset_by).to_lowercase()),
+        );
+        add(
+            "tags",
+            (!self.tags.is_empty()).then(|| self.tags.join(", ")),
+        );
+        add(
+            "suggestions",
+            (!self.suggestions.is_empty()).then(|| self.suggestions.len().to_string()),
+        );
+        add(
+            "suggestions_file",
+            self.suggestions_file
+                .as_ref()
+                .map(|p| p.display().to_string()),
+        );
+        add("reset_at", self.reset_at.as_ref().map(hhmm));
+        add(
+            "at",
+            (!self.at.is_empty()).then(|| self.at.iter().map(hhmm).collect::<Vec<_>>().join(", ")),
+        );
+        add(
+            "thresholds",
+            (!self.thresholds.is_empty()).then(|| {
+                self.thresholds
+                    .iter()
+                    .map(mmss)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }),
+        );
+        add("nag_after", self.nag_after.as_ref().map(mmss));
+        add(
+            "credit",
+            self.credit.as_ref().map(|c| {
+                format!(
+                    "pauses of {} within {} of going off",
+                    mmss(&c.min_break),
+                    mmss(&c.within)
+                )
+            }),
+        );
+        add("speak", self.speak.then(|| "true".to_string()));
+        add("border", self.border.then(|| "true".to_string()));
+        add("led", self.led.clone());
+        add(
+            "audio",
+            self.audio.map(|a| match a {
+                AudioPolicy::Activity => "activity".to_string(),
+                AudioPolicy::PreventReset => "prevent_reset".to_string(),
+            }),
+        );
+        add("match", self.pattern.clone());
+        add(
+            "pause_in",
+            (!self.pause_in.is_empty()).then(|| self.pause_in.join(", ")),
+        );
+        add(
+            "count_in",
+            (!self.count_in.is_empty()).then(|| self.count_in.join(", ")),
+        );
+        settings
+    }
+
+    pub fn countdown(name: String, duration: Duration) -> Self {
+        Self {
+            name,
+            kind: TimerType::Countdown,
+            interval: duration,
+            notify: true,
+            ..Default::default()
+        }
+    }
+
+    /// Local time of day at which a daily timer starts a new day
+    pub fn reset_at(&self) -> Duration {
+        self.reset_at.unwrap_or(Duration::from_secs(4 * 60 * 60))
+    }
+
+    /// Returns the configured suggestions including the ones from the suggestions file
+    pub fn load_suggestions(&self) -> Result<Vec<String>> {
+        let mut suggestions = self.suggestions.clone();
+        if let Some(path) = &self.suggestions_file {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read suggestions file {path:?}"))?;
+            suggestions.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        Ok(suggestions)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivitySource {
+    /// Not implemented yet and always skipped, accepted so configurations can list it
+    Wayland,
+    X11,
+    /// Input devices through the activity daemon
+    Evdev,
+    Logind,
+    /// Idle monitor of GNOME Shell over D-Bus
+    Gnome,
+    /// Idle time of KDE Plasma over D-Bus
+    Kde,
+}
+
+impl std::fmt::Display for ActivitySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActivitySource::Wayland => write!(f, "wayland"),
+            ActivitySource::X11 => write!(f, "x11"),
+            ActivitySource::Evdev => write!(f, "evdev"),
+            ActivitySource::Logind => write!(f, "logind"),
+            ActivitySource::Gnome => write!(f, "gnome"),
+            ActivitySource::Kde => write!(f, "kde"),
+        }
+    }
+}
+
+/// What happens to the timers when the system was suspended
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuspendPolicy {
+    /// Reset the timers, the suspend counts as a break
+    #[default]
+    Reset,
+    /// Keep the clocks as they were before the suspend
+    Pause,
+    /// Count the time of the suspend as well
+    Continue,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Activity {
+    /// Activity sources in order of preference, the first available one is used
+    #[serde(default = "default_sources")]
+    pub source: Vec<ActivitySource>,
     #[serde(
         default,
         with = "mmss_format_opt",
@@ -38,21 +372,382 @@ pub struct Activity {
         skip_serializing_if = "Option::is_none"
     )]
     pub inactivity_reset: Option<Duration>,
+    /// Keyboard events per minute at which adaptive timers run at normal speed
+    #[serde(default = "default_intensity_baseline")]
+    pub intensity_baseline: f64,
+    /// Shell command run periodically that exits successfully when the user is present,
+    /// e.g. to check a webcam or the proximity of a phone
+    #[serde(default)]
+    pub presence_command: Option<String>,
+    /// Time between runs of the presence command
+    #[serde(
+        default,
+        with = "mmss_format_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub presence_interval: Option<Duration>,
+    #[serde(default)]
+    pub on_suspend: SuspendPolicy,
+}
+
+fn default_sources() -> Vec<ActivitySource> {
+    vec![ActivitySource::Evdev]
+}
+
+fn default_intensity_baseline() -> f64 {
+    200.0
 }
 
 impl Default for Activity {
     fn default() -> Self {
         Self {
+            source: default_sources(),
             inactivity_pause: Some(Duration::from_secs(10)),
             inactivity_reset: Some(Duration::from_secs(5 * 60)),
+            intensity_baseline: default_intensity_baseline(),
+            presence_command: None,
+            presence_interval: None,
+            on_suspend: SuspendPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Logging {
+    /// Log to journald, by default enabled when running under systemd
+    #[serde(default)]
+    pub journald: Option<bool>,
+    /// Log file, which is rotated daily unless a rotation is configured
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// Append-only log of timer and activity events as JSON lines
+    #[serde(default)]
+    pub events: Option<PathBuf>,
+    /// Rotation of the log file and the event log, both are reopened on `SIGUSR1`
+    #[serde(default)]
+    pub rotation: Option<Rotation>,
+    /// Number of recent changes by clients shown by `movebeam status`, with the user and
+    /// process that made them, they are logged regardless
+    #[serde(default)]
+    pub audit: usize,
+}
+
+/// Limits after which a file is moved to `<file>.1`, the older ones to `<file>.2` and so on
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Rotation {
+    /// Size in bytes, e.g. `10M`
+    #[serde(default, with = "size_format_opt")]
+    pub max_size: Option<u64>,
+    /// Age of the oldest entry, e.g. `7d`
+    #[serde(default, with = "days_format_opt")]
+    pub max_age: Option<u32>,
+    /// Number of rotated files that are kept
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_age: None,
+            keep: default_keep(),
         }
     }
 }
 
+fn default_keep() -> usize {
+    5
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Notifications {
+    /// Minimum time between notifications, timers going off in between are grouped
+    #[serde(
+        default,
+        with = "mmss_format_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_spacing: Option<Duration>,
+    /// ICS calendar file, notifications are held back during its events
+    #[serde(default)]
+    pub calendar: Option<PathBuf>,
+    /// Shell command that exits successfully while notifications should be held back, it runs
+    /// every 30 seconds in the background
+    #[serde(default)]
+    pub quiet_command: Option<String>,
+    /// Hold back notifications while the user is typing intensely
+    #[serde(default)]
+    pub flow: Option<FlowConfig>,
+    /// Send a low-urgency notification when returning after the timers were reset because
+    /// of inactivity
+    #[serde(default)]
+    pub welcome_back: bool,
+}
+
+/// Detection of sustained intense typing, which needs the input intensity from the activity
+/// daemon started with `--privacy counts`
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlowConfig {
+    /// Keyboard events per minute from which the user counts as typing intensely
+    #[serde(default = "default_flow_intensity")]
+    pub intensity: f64,
+    /// Time of intense typing after which the user is in flow
+    #[serde(default = "default_flow_after", with = "mmss_format")]
+    pub after: Duration,
+    /// Time without input that counts as a natural pause to send the notifications in
+    #[serde(default = "default_flow_pause", with = "mmss_format")]
+    pub pause: Duration,
+}
+
+fn default_flow_intensity() -> f64 {
+    150.0
+}
+
+fn default_flow_after() -> Duration {
+    Duration::from_secs(10 * 60)
+}
+
+fn default_flow_pause() -> Duration {
+    Duration::from_secs(3)
+}
+
+/// Sharing of the timers with other machines through a synchronized file
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncConfig {
+    /// File shared between the machines, e.g. in a Syncthing folder
+    pub file: PathBuf,
+    /// Time between synchronizations
+    #[serde(
+        default,
+        with = "mmss_format_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interval: Option<Duration>,
+}
+
+/// Read-only access to the timers over the network
+#[derive(Debug, Clone, Deserialize)]
+pub struct Remote {
+    /// Address to listen on, e.g. `0.0.0.0:7878`
+    pub listen: SocketAddr,
+    /// Shared secret clients have to send with each message
+    pub token: String,
+}
+
+/// Web dashboard and JSON API served by the daemon, requires the `dashboard` feature
+#[derive(Debug, Clone, Deserialize)]
+pub struct DashboardConfig {
+    /// Address to serve on, e.g. `127.0.0.1:8080`
+    pub listen: SocketAddr,
+    /// Token that resets have to send as `Authorization: Bearer <token>`, the page takes it
+    /// from its URL as `#token=<token>`
+    ///
+    /// Without a token resets are only allowed on a loopback address, from the dashboard page
+    /// itself or from clients that aren't browsers.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Activity history
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsConfig {
+    /// Number of days the history is kept, e.g. `90d`
+    #[serde(default, with = "days_format_opt")]
+    pub retention: Option<u32>,
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    /// Shortest pause between active periods that counts as a break for the goals
+    #[serde(default = "default_break_length", with = "mmss_format")]
+    pub break_length: Duration,
+    /// Local time (hh:mm) at which a notification tells how the goals of the day went
+    #[serde(default, with = "hhmm_format_opt")]
+    pub goal_summary: Option<Duration>,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            retention: None,
+            goals: Vec::new(),
+            break_length: default_break_length(),
+            goal_summary: None,
+        }
+    }
+}
+
+fn default_break_length() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+/// Target for the activity of a day, week or month, set one of the limits
+#[derive(Debug, Clone, Deserialize)]
+pub struct Goal {
+    /// Minimum number of breaks
+    #[serde(default)]
+    pub min_breaks: Option<u32>,
+    /// Maximum active time, e.g. `9h`
+    #[serde(default, with = "hours_format_opt")]
+    pub max_active: Option<Duration>,
+    /// Minimum active time, e.g. `2h30`
+    #[serde(default, with = "hours_format_opt")]
+    pub min_active: Option<Duration>,
+    #[serde(default)]
+    pub per: GoalPeriod,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalPeriod {
+    #[default]
+    Day,
+    /// Since Monday
+    Week,
+    Month,
+}
+
+/// Defaults for the command line interface
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CliConfig {
+    /// Show the remaining time by default
+    #[serde(default)]
+    pub remaining: bool,
+    /// Only output plain ASCII characters without colors, e.g. for braille displays
+    #[serde(default)]
+    pub ascii: bool,
+    /// End the list with the timer that is due next
+    #[serde(default)]
+    pub summary: bool,
+}
+
+/// How durations are written in the human-readable output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum DurationStyle {
+    /// Minutes and seconds, e.g. `65:30`, totals of a day are written as `1h05`
+    #[default]
+    #[serde(rename = "mm:ss")]
+    MinutesSeconds,
+    /// Hours and minutes, e.g. `1:05`
+    #[serde(rename = "h:mm")]
+    HoursMinutes,
+    /// Largest units first, e.g. `1h 5m` or `5m 30s`
+    #[serde(rename = "humanized")]
+    Humanized,
+}
+
+/// Clock that times of the day are written on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum ClockStyle {
+    /// E.g. `17:30`
+    #[default]
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+    /// E.g. `5:30 PM`
+    #[serde(rename = "12h")]
+    TwelveHour,
+}
+
+/// Rendering of durations and times in the notifications and the command line output, the
+/// porcelain and CSV output keep their fixed formats
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FormatConfig {
+    #[serde(default)]
+    pub duration: DurationStyle,
+    #[serde(default)]
+    pub clock: ClockStyle,
+}
+
+/// Language of the notifications and the command line output
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LanguageConfig {
+    /// Locale to use instead of the one of the environment, e.g. `nl` or `de-AT`
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Messages replacing the bundled translations by their ID, in the Fluent syntax, e.g.
+    /// `timer-went-off = "Time for { $name }"`
+    #[serde(default)]
+    pub messages: BTreeMap<String, String>,
+}
+
+/// Status bar output
+#[derive(Debug, Clone, Deserialize)]
+pub struct BarConfig {
+    /// Styles that `movebeam bar --style <name>` takes its options from
+    #[serde(default)]
+    pub styles: BTreeMap<String, BarStyle>,
+    /// Times per second a blinking bar switches between filled and empty, the phase is kept
+    /// by the daemon so that all bars blink at once
+    #[serde(default = "default_blink_rate")]
+    pub blink_rate: f64,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        Self {
+            styles: BTreeMap::new(),
+            blink_rate: default_blink_rate(),
+        }
+    }
+}
+
+fn default_blink_rate() -> f64 {
+    1.0
+}
+
+/// Options of the status bar, the unset ones are taken from the command line or the defaults
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BarStyle {
+    pub size: Option<usize>,
+    pub fill: Option<String>,
+    pub empty: Option<String>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+    #[serde(default)]
+    pub blink: bool,
+    /// Color the bar by the progress of the timer
+    pub colors: Option<bool>,
+    /// Text in front of the bar
+    pub label: Option<String>,
+    /// Show the remaining time behind the bar
+    #[serde(default)]
+    pub show_time: bool,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub activity: Option<Activity>,
+    #[serde(default)]
+    pub logging: Logging,
+    #[serde(default)]
+    pub notifications: Notifications,
+    #[serde(default)]
+    pub cli: CliConfig,
+    #[serde(default)]
+    pub language: LanguageConfig,
+    #[serde(default)]
+    pub format: FormatConfig,
+    #[serde(default)]
+    pub bar: BarConfig,
+    #[serde(default)]
+    pub sync: Option<SyncConfig>,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub remote: Option<Remote>,
+    /// File the configuration was loaded from, not set for the default configuration
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub dashboard: Option<DashboardConfig>,
+    /// Also answer read-only queries on a datagram socket, each datagram is one message
+    #[serde(default)]
+    pub datagram: bool,
+    /// Only answer queries on the daemon socket, so scripts can't change the timers, changes
+    /// are accepted on the control socket from clients that send the token of the owner-only
+    /// token file next to it, like `movebeam --control` does
+    #[serde(default)]
+    pub read_only: bool,
     pub timers: Vec<TimerConfig>,
 }
 
@@ -60,6 +755,19 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             activity: Some(Activity::default()),
+            logging: Logging::default(),
+            notifications: Notifications::default(),
+            cli: CliConfig::default(),
+            language: LanguageConfig::default(),
+            format: FormatConfig::default(),
+            bar: BarConfig::default(),
+            sync: None,
+            stats: StatsConfig::default(),
+            remote: None,
+            path: None,
+            dashboard: None,
+            datagram: false,
+            read_only: false,
             timers: vec![
                 TimerConfig {
                     name: "move".to_string(),
@@ -67,6 +775,7 @@ impl Default for Config {
                     suggested: None,
                     duration: Some(Duration::from_secs(60)),
                     notify: true,
+                    ..Default::default()
                 },
                 TimerConfig {
                     name: "break".to_string(),
@@ -74,6 +783,7 @@ impl Default for Config {
                     suggested: Some(Duration::from_secs(55 * 60)),
                     duration: Some(Duration::from_secs(10 * 60)),
                     notify: true,
+                    ..Default::default()
                 },
             ],
         }
@@ -85,8 +795,14 @@ impl Config {
         if path.exists() {
             let config_str =
                 fs::read_to_string(path).with_context(|| "Failed to read configuration file")?;
-            Ok(toml::from_str::<Self>(&config_str)
-                .with_context(|| "Failed to read configuration file")?)
+            let mut table = toml::from_str::<toml::Table>(&config_str)
+                .with_context(|| "Failed to read configuration file")?;
+            apply_templates(&mut table)?;
+            let mut config = toml::Value::Table(table)
+                .try_into::<Self>()
+                .with_context(|| "Failed to read configuration file")?;
+            config.path = Some(path.to_path_buf());
+            Ok(config)
         } else {
             info!("No config file found, using default configuration");
             Ok(Config::default())
@@ -94,6 +810,116 @@ impl Config {
     }
 }
 
+/// Fills in the fields of timers that extend a `[template.<name>]` block
+///
+/// Templates can extend other templates, the fields of the timer take precedence.
+fn apply_templates(config: &mut toml::Table) -> Result<()> {
+    let templates = match config.remove("template") {
+        Some(toml::Value::Table(templates)) => templates,
+        Some(_) => bail!("Templates must be tables, e.g. [template.<name>]"),
+        None => toml::Table::new(),
+    };
+    let Some(toml::Value::Array(timers)) = config.get_mut("timers") else {
+        return Ok(());
+    };
+    for timer in timers.iter_mut().filter_map(toml::Value::as_table_mut) {
+        let mut seen = Vec::new();
+        let mut extends = timer.get("extends").cloned();
+        while let Some(name) = extends {
+            let name = name
+                .as_str()
+                .context("Timer extends a template by its name")?
+                .to_string();
+            if seen.contains(&name) {
+                bail!("Template {name} extends itself");
+            }
+            let Some(toml::Value::Table(template)) = templates.get(&name) else {
+                bail!("Unknown template {name}");
+            };
+            for (key, value) in template {
+                timer.entry(key).or_insert_with(|| value.clone());
+            }
+            extends = template.get("extends").cloned();
+            seen.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Parses a duration in the `mm:ss` format
+pub fn parse_mmss(str: &str) -> Result<Duration, String> {
+    let center = str
+        .find(':')
+        .ok_or_else(|| "missing ':' splitter on duration".to_string())?;
+    let mins = str[..center]
+        .parse::<u64>()
+        .map_err(|e| format!("failed to parse left integer: {}", e))?;
+    let secs = str[center + 1..]
+        .parse::<u64>()
+        .map_err(|e| format!("failed to parse right integer: {}", e))?;
+    Ok(Duration::from_secs(mins * 60 + secs))
+}
+
+/// Parses a time of day in the `hh:mm` format
+pub fn parse_hhmm(str: &str) -> Result<Duration, String> {
+    let (hours, mins) = str
+        .split_once(':')
+        .ok_or_else(|| "missing ':' splitter on time".to_string())?;
+    let hours = hours
+        .parse::<u64>()
+        .map_err(|e| format!("failed to parse hours: {}", e))?;
+    let mins = mins
+        .parse::<u64>()
+        .map_err(|e| format!("failed to parse minutes: {}", e))?;
+    if hours >= 24 || mins >= 60 {
+        return Err(format!("invalid time of day '{str}'"));
+    }
+    Ok(Duration::from_secs(hours * 60 * 60 + mins * 60))
+}
+
+/// Parses a number of days in the `<n>d` format
+pub fn parse_days(str: &str) -> Result<u32, String> {
+    str.strip_suffix('d')
+        .ok_or_else(|| "missing 'd' suffix on days".to_string())?
+        .parse::<u32>()
+        .map_err(|e| format!("failed to parse days: {}", e))
+}
+
+/// Parses a number of hours in the `<h>h` or `<h>h<mm>` format, e.g. `1h30`
+pub fn parse_hours(str: &str) -> Result<Duration, String> {
+    let (hours, mins) = str
+        .split_once('h')
+        .ok_or_else(|| "missing 'h' in hours".to_string())?;
+    let hours = hours
+        .parse::<u64>()
+        .map_err(|e| format!("failed to parse hours: {}", e))?;
+    let mins = if mins.is_empty() {
+        0
+    } else {
+        mins.parse::<u64>()
+            .map_err(|e| format!("failed to parse minutes: {}", e))?
+    };
+    if mins >= 60 {
+        return Err(format!("invalid minutes in '{str}'"));
+    }
+    Ok(Duration::from_secs(hours * 60 * 60 + mins * 60))
+}
+
+/// Parses a size in bytes with an optional `K`, `M` or `G` suffix, e.g. `10M`
+pub fn parse_size(str: &str) -> Result<u64, String> {
+    let (number, unit) = match str.char_indices().last() {
+        Some((i, 'K')) => (&str[..i], 1 << 10),
+        Some((i, 'M')) => (&str[..i], 1 << 20),
+        Some((i, 'G')) => (&str[..i], 1 << 30),
+        _ => (str, 1),
+    };
+    let n = number
+        .parse::<u64>()
+        .map_err(|e| format!("failed to parse size: {}", e))?;
+    n.checked_mul(unit)
+        .ok_or_else(|| format!("size {str} is too large"))
+}
+
 mod mmss_format {
     use serde::{de::Error, Deserialize, Deserializer};
     use std::time::Duration;
@@ -103,17 +929,7 @@ mod mmss_format {
         D: Deserializer<'de>,
     {
         let str = String::deserialize(deserializer)?;
-        let center = str
-            .find(':')
-            .ok_or_else(|| Error::custom("missing ':' splitter on duration"))?;
-        let mins = &str[..center]
-            .parse::<u64>()
-            .map_err(|e| Error::custom(format!("failed to parse left integer: {}", e)))?;
-        let secs = &str[center + 1..]
-            .parse::<u64>()
-            .map_err(|e| Error::custom(format!("failed to parse right integer: {}", e)))?;
-
-        Ok(Duration::from_secs(mins * 60 + secs))
+        super::parse_mmss(&str).map_err(Error::custom)
     }
 }
 
@@ -132,3 +948,159 @@ mod mmss_format_opt {
         }
     }
 }
+
+mod mmss_format_vec {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use std::time::Duration;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| super::parse_mmss(s).map_err(Error::custom))
+            .collect()
+    }
+}
+
+mod hhmm_format_opt {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use std::time::Duration;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        super::parse_hhmm(&str).map(Some).map_err(Error::custom)
+    }
+}
+
+mod hhmm_format_vec {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use std::time::Duration;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| super::parse_hhmm(s).map_err(Error::custom))
+            .collect()
+    }
+}
+
+mod hours_format_opt {
+    use serde::{de::Error, Deserialize, Deserializer};
+    use std::time::Duration;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        super::parse_hours(&str).map(Some).map_err(Error::custom)
+    }
+}
+
+mod size_format_opt {
+    use serde::{de::Error, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        super::parse_size(&str).map(Some).map_err(Error::custom)
+    }
+}
+
+mod days_format_opt {
+    use serde::{de::Error, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        super::parse_days(&str).map(Some).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_with_units() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("10K"), Ok(10 << 10));
+        assert_eq!(parse_size("10M"), Ok(10 << 20));
+        assert_eq!(parse_size("2G"), Ok(2 << 30));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10k").is_err());
+        assert!(parse_size("-1").is_err());
+        assert_eq!(
+            parse_size("17179869184G"),
+            Err("size 17179869184G is too large".to_string())
+        );
+    }
+
+    fn timers(source: &str) -> Result<toml::Table> {
+        let mut config: toml::Table = toml::from_str(source)?;
+        apply_templates(&mut config)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn templates_fill_in_missing_fields() {
+        let config = timers(
+            r#"
+            [template.short]
+            interval = "20:00"
+            notify = true
+
+            [template.eyes]
+            extends = "short"
+            interval = "25:00"
+            tags = ["eyes"]
+
+            [[timers]]
+            name = "eyes"
+            extends = "eyes"
+            notify = false
+            "#,
+        )
+        .unwrap();
+        assert!(config.get("template").is_none());
+        let timer = config["timers"][0].as_table().unwrap();
+        // The timer wins over the templates, and a template over the one it extends
+        assert_eq!(timer["notify"].as_bool(), Some(false));
+        assert_eq!(timer["interval"].as_str(), Some("25:00"));
+        assert_eq!(timer["tags"][0].as_str(), Some("eyes"));
+    }
+
+    #[test]
+    fn templates_in_a_cycle() {
+        let error = timers(
+            r#"
+            [template.a]
+            extends = "b"
+
+            [template.b]
+            extends = "a"
+
+            [[timers]]
+            name = "move"
+            extends = "a"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Template a extends itself");
+        assert!(timers("[[timers]]\nname = \"move\"\nextends = \"missing\"").is_err());
+    }
+}
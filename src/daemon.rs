@@ -0,0 +1,2559 @@
+//! Timer engine of moved, which serves the timers on the daemon socket
+#[cfg(feature = "border")]
+use crate::border::Border;
+#[cfg(feature = "dashboard")]
+use crate::dashboard::{Dashboard, Overview, Reply, Request, TimerOverview};
+use crate::{
+    activity::{ActivityBackend, AudioMonitor, PresenceHook},
+    calendar::QuietPeriods,
+    config::{
+        Activity, ActivitySource, AudioPolicy, Config, CountMode, ResetSource, SuspendPolicy,
+        TimerConfig, TimerType,
+    },
+    events::{Event, EventLog, ResetReason},
+    format::{format_duration, format_spoken, Formatter},
+    history::Period,
+    led::Led,
+    msg::{
+        AuditEntry, DaemonStatus, Encoding, Message, Response, ResponseError, SortKey,
+        TimerDetails, TimerInfo, TimerSnapshot, TimerStatus, CAPABILITIES,
+    },
+    persistence::{DailyClock, DayCounters, PersistentState, SharedClock, SharedState},
+    socket::{Answer, DatagramServer, Peer, Publisher, SocketServer},
+    window::{FocusedWindow, WindowTracker},
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Timelike};
+use mio::Waker;
+use parking_lot::Mutex;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    path::{Path, PathBuf},
+    process::Child,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+use tracing::{error, info, trace, warn};
+
+const HEARTBEAT: Duration = Duration::from_secs(1);
+/// Interval at which the persistent state is saved
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// Interval at which the history is pruned to the retention
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Default interval at which the timers are synchronized with other machines
+const SYNC_INTERVAL: Duration = Duration::from_secs(10);
+/// Names within this edit distance are suggested when a timer isn't found
+const SIMILAR_DISTANCE: usize = 2;
+/// Input within this time makes the heartbeat count for timers that only count active time
+const ACTIVE_THRESHOLD: Duration = Duration::from_secs(2);
+/// Bounds of the time between attempts to send notifications while there is no notification daemon
+const NOTIFY_RETRY_MIN: Duration = Duration::from_secs(5);
+const NOTIFY_RETRY_MAX: Duration = Duration::from_secs(5 * 60);
+/// Longest time notifications of timers without a maximum deferral are held back in flow
+const MAX_DEFER: Duration = Duration::from_secs(10 * 60);
+/// Time without an answer from the activity source after which a warning is logged
+const ACTIVITY_STALE: Duration = Duration::from_secs(60);
+/// Time the system has to be suspended for the suspend policy to apply
+const SUSPEND_GAP: Duration = Duration::from_secs(30);
+/// Bounds of the clock speed of adaptive timers
+const ADAPTIVE_MIN: f64 = 0.5;
+const ADAPTIVE_MAX: f64 = 2.0;
+/// Time over which the overdue border reaches full intensity
+#[cfg(feature = "border")]
+const BORDER_RAMP: Duration = Duration::from_secs(10 * 60);
+
+struct TimerState {
+    clock: Duration,
+    went_off: bool,
+    /// Remaining time of a break started by the user
+    on_break: Option<Duration>,
+    /// Why the clock stood still in the last update
+    paused: Option<TimerStatus>,
+    suggestions: Vec<String>,
+    next_suggestion: usize,
+    /// Day that a daily timer is counting, or of the last scheduled reset
+    day: Option<NaiveDate>,
+    /// Number of notification points a daily timer went past
+    thresholds_passed: usize,
+    /// Unix time of the last reset by the user, used to synchronize with other machines
+    reset_epoch: u64,
+    /// Focused windows counted by an app timer
+    pattern: Option<Regex>,
+    /// Nagbar shown since the timer is badly overdue
+    nagbar: Option<Child>,
+    nagged: bool,
+    /// LED pulsing while the timer is overdue
+    led: Option<Led>,
+    /// Time between the previous and the next time of a reminder
+    span: Option<Duration>,
+    /// Day of the counters below, which are kept across resets
+    today: Option<NaiveDate>,
+    fired_today: u32,
+    elapsed_today: Duration,
+    config: TimerConfig,
+}
+
+impl TimerState {
+    fn new(config: TimerConfig) -> Result<Self> {
+        let pattern = match (&config.pattern, config.kind) {
+            (Some(pattern), _) => Some(
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid match of timer {}", config.name))?,
+            ),
+            (None, TimerType::App) => bail!("App timer {} has no match", config.name),
+            (None, _) => None,
+        };
+        match config.kind {
+            TimerType::Reminder if config.at.is_empty() => {
+                bail!("Reminder {} has no times", config.name)
+            }
+            TimerType::Break | TimerType::Daily | TimerType::App if config.interval.is_zero() => {
+                bail!("Timer {} has no interval", config.name)
+            }
+            _ => {}
+        }
+        let led = config.led.as_deref().and_then(|name| {
+            Led::open(name)
+                .inspect_err(|e| warn!("Failed to open LED of timer {}: {e:#}", config.name))
+                .ok()
+        });
+        Ok(Self {
+            clock: Duration::ZERO,
+            went_off: false,
+            on_break: None,
+            paused: None,
+            suggestions: config.load_suggestions()?,
+            next_suggestion: 0,
+            day: None,
+            thresholds_passed: 0,
+            reset_epoch: 0,
+            pattern,
+            nagbar: None,
+            nagged: false,
+            led,
+            span: None,
+            today: None,
+            fired_today: 0,
+            elapsed_today: Duration::ZERO,
+            config,
+        })
+    }
+
+    /// Interval of the timer, for reminders the time between the previous and the next time
+    fn interval(&self) -> Duration {
+        self.span.unwrap_or(self.config.interval)
+    }
+
+    fn remaining(&self) -> Duration {
+        self.interval().saturating_sub(self.clock)
+    }
+
+    /// Time since the timer became overdue, reminders are overdue until they are reset
+    fn overdue_time(&self) -> Option<Duration> {
+        let grace = self.config.grace.unwrap_or_default();
+        let start = match self.config.kind {
+            TimerType::Reminder if self.went_off => grace,
+            TimerType::Reminder => return None,
+            _ => self.config.interval + grace,
+        };
+        self.clock.checked_sub(start)
+    }
+
+    fn is_overdue(&self) -> bool {
+        self.overdue_time().is_some()
+    }
+
+    /// Intensity of the overdue border, which starts faint and grows over time
+    #[cfg(feature = "border")]
+    fn border_intensity(&self) -> f32 {
+        let Some(overdue) = self.overdue_time() else {
+            return 0.0;
+        };
+        if !self.config.border || self.on_break.is_some() {
+            return 0.0;
+        }
+        0.25 + 0.75 * (overdue.as_secs_f32() / BORDER_RAMP.as_secs_f32()).min(1.0)
+    }
+
+    fn status(&self) -> TimerStatus {
+        if self.on_break.is_some() {
+            TimerStatus::PausedBreak
+        } else if let Some(paused) = self.paused {
+            paused
+        } else if self.went_off {
+            TimerStatus::Fired
+        } else {
+            TimerStatus::Running
+        }
+    }
+
+    fn info(&self) -> TimerInfo {
+        TimerInfo {
+            elapsed: self.clock,
+            interval: self.interval(),
+            overdue: self.is_overdue(),
+            status: self.status(),
+            deferred: None,
+            blink: true,
+            fired_today: self.fired_today,
+            elapsed_today: self.elapsed_today,
+        }
+    }
+
+    /// Adds the progress since the clock and whether it went off were last seen to the
+    /// counters of the day
+    fn count_today(&mut self, day: NaiveDate, clock: Duration, went_off: bool) {
+        if self.today != Some(day) {
+            self.today = Some(day);
+            self.fired_today = 0;
+            self.elapsed_today = Duration::ZERO;
+        }
+        self.elapsed_today += self.clock.saturating_sub(clock);
+        if self.went_off && !went_off {
+            self.fired_today += 1;
+        }
+    }
+
+    /// Returns the next exercise suggestion in turn
+    fn suggestion(&mut self) -> String {
+        if self.suggestions.is_empty() {
+            return crate::tr!("take-a-break");
+        }
+        let suggestion = self.suggestions[self.next_suggestion].clone();
+        self.next_suggestion = (self.next_suggestion + 1) % self.suggestions.len();
+        suggestion
+    }
+
+    /// Zeroes the clock, the reset takes precedence over older clocks on other machines
+    fn reset(&mut self) {
+        self.clock = Duration::ZERO;
+        self.went_off = false;
+        self.on_break = None;
+        self.thresholds_passed = 0;
+        // Later than an epoch adopted from another machine within the same second
+        self.reset_epoch = unix_time().as_secs().max(self.reset_epoch + 1);
+        self.dismiss_nagbar();
+    }
+
+    /// Closes the nagbar, it is shown again once the timer is badly overdue
+    fn dismiss_nagbar(&mut self) {
+        if let Some(mut nagbar) = self.nagbar.take() {
+            let _ = nagbar.kill();
+            let _ = nagbar.wait();
+        }
+        self.nagged = false;
+    }
+
+    /// Number of thresholds of a daily timer the clock is past, including the interval
+    fn passed_thresholds(&self) -> usize {
+        self.config
+            .thresholds
+            .iter()
+            .chain([&self.config.interval])
+            .filter(|t| self.clock > **t)
+            .count()
+    }
+}
+
+/// Clock of a timer from before a reset by the user, restored by an undo
+struct ResetUndo {
+    name: String,
+    clock: Duration,
+    went_off: bool,
+    on_break: Option<Duration>,
+    thresholds_passed: usize,
+    reset_epoch: u64,
+}
+
+impl ResetUndo {
+    fn of(timer: &TimerState) -> Self {
+        Self {
+            name: timer.config.name.clone(),
+            clock: timer.clock,
+            went_off: timer.went_off,
+            on_break: timer.on_break,
+            thresholds_passed: timer.thresholds_passed,
+            reset_epoch: timer.reset_epoch,
+        }
+    }
+
+    fn restore(self, timer: &mut TimerState) {
+        timer.clock = self.clock;
+        timer.went_off = self.went_off;
+        timer.on_break = self.on_break;
+        timer.thresholds_passed = self.thresholds_passed;
+        timer.reset_epoch = self.reset_epoch;
+    }
+}
+
+/// Writes the event to the event log if there is one
+fn log_event(events: &mut Option<EventLog>, event: Event) {
+    if let Some(events) = events {
+        if let Err(e) = events.write(&event) {
+            error!("Failed to write event: {e:#}");
+        }
+    }
+}
+
+/// Name of a running process and of its parent, which is usually the script that ran a client,
+/// e.g. `movebeam started by sh`
+fn process_name(pid: u32) -> Option<String> {
+    let name = |pid: u32| {
+        fs::read_to_string(format!("/proc/{pid}/comm"))
+            .ok()
+            .map(|name| name.trim_end().to_string())
+    };
+    // The parent ID is the second field after the name in parentheses
+    let parent = fs::read_to_string(format!("/proc/{pid}/stat"))
+        .ok()
+        .and_then(|stat| {
+            let (_, fields) = stat.rsplit_once(')')?;
+            fields.split_whitespace().nth(1)?.parse().ok()
+        })
+        .and_then(name);
+    Some(match (name(pid)?, parent) {
+        (name, Some(parent)) => format!("{name} started by {parent}"),
+        (name, None) => name,
+    })
+}
+
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// What the clients of a socket may do
+enum Access {
+    /// Local clients that may query and change everything
+    Full,
+    /// Local clients that may only query
+    ReadOnly,
+    /// Clients that have to send the token, to change the timers as well when `full`
+    Token { token: String, full: bool },
+}
+
+/// Compares tokens in constant time to not reveal how much of a guess is right
+pub(crate) fn tokens_equal(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Writes a new random token that only the owner can read, clients of the control socket send
+/// it to change the timers
+fn write_control_token(path: &Path) -> Result<String> {
+    let mut bytes = [0; 32];
+    File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut bytes))
+        .context("Failed to generate the control token")?;
+    let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    // A new file, so no other process keeps a descriptor of the old token
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("Failed to remove the old token {path:?}"))
+        }
+        _ => {}
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(token.as_bytes()))
+        .with_context(|| format!("Failed to write the control token to {path:?}"))?;
+    Ok(token)
+}
+
+/// Removes the audit trail from status responses, only clients with full access may see which
+/// processes changed the timers
+fn hide_audit(response: &mut Response) {
+    match response {
+        Response::Status(status) => status.audit.clear(),
+        Response::Batch(responses) => responses.iter_mut().for_each(hide_audit),
+        Response::Reply(_, response) => hide_audit(response),
+        _ => {}
+    }
+}
+
+/// Number of single-character edits between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the local date, where a day starts at the given time of day
+fn current_day(reset_at: Duration) -> NaiveDate {
+    day_of(&Local::now(), reset_at)
+}
+
+/// Date of the day the time belongs to, where a day starts at the given time of day
+fn day_of<Tz: TimeZone>(time: &DateTime<Tz>, reset_at: Duration) -> NaiveDate {
+    (time.clone() - chrono::Duration::from_std(reset_at).unwrap_or_default()).date_naive()
+}
+
+/// Times of the day at which a reminder last went off and goes off next, from midnight to
+/// midnight when there is none
+fn reminder_bounds(at: &[Duration], time: Duration) -> (Duration, Duration) {
+    let previous = at.iter().filter(|t| **t <= time).max();
+    let next = at.iter().filter(|t| **t > time).min();
+    (
+        previous.copied().unwrap_or_default(),
+        next.copied().unwrap_or(Duration::from_secs(24 * 60 * 60)),
+    )
+}
+
+/// Timers of the mock mode with their clocks and remaining break time
+const MOCK_TIMERS: &[(&str, TimerType, u64, u64, Option<u64>)] = &[
+    ("move", TimerType::Break, 50 * 60, 12 * 60 + 34, None),
+    ("eyes", TimerType::Break, 20 * 60, 21 * 60, None),
+    ("stretch", TimerType::Break, 30 * 60, 30 * 60, Some(3 * 60)),
+    (
+        "screen",
+        TimerType::Daily,
+        8 * 60 * 60,
+        3 * 60 * 60 + 21 * 60,
+        None,
+    ),
+];
+/// Idle times the mock mode cycles through
+const MOCK_IDLE: &[Duration] = &[
+    Duration::ZERO,
+    Duration::from_secs(45),
+    Duration::from_secs(5),
+];
+const MOCK_IDLE_STEP: Duration = Duration::from_secs(20);
+
+/// Configuration of the mock mode, without notifications or resets that change the clocks
+pub fn mock_config() -> Config {
+    Config {
+        activity: Some(Activity {
+            inactivity_reset: None,
+            ..Activity::default()
+        }),
+        timers: MOCK_TIMERS
+            .iter()
+            .map(|(name, kind, interval, _, _)| TimerConfig {
+                name: name.to_string(),
+                kind: *kind,
+                interval: Duration::from_secs(*interval),
+                duration: Some(Duration::from_secs(5 * 60)),
+                ..TimerConfig::default()
+            })
+            .collect(),
+        ..Config::default()
+    }
+}
+
+/// Simulated clock with injected activity, used for testing and demos
+pub struct Simulation {
+    speed: f64,
+    /// Total simulated time
+    time: Duration,
+    /// Time the clock still has to be advanced by on the next update
+    pending: Duration,
+    /// Simulated time since the last injected input
+    input_elapsed: Duration,
+    /// Input events to replay as offsets from the start
+    replay: VecDeque<Duration>,
+    /// Start of the mock mode, whose idle time follows the script in real time
+    mock: Option<Instant>,
+}
+
+impl Simulation {
+    pub fn new(speed: f64, replay: VecDeque<Duration>) -> Self {
+        info!("Simulating clock at {speed}x speed");
+        if !replay.is_empty() {
+            info!("Replaying {} input events", replay.len());
+        }
+        Self {
+            speed,
+            time: Duration::ZERO,
+            pending: Duration::ZERO,
+            input_elapsed: Duration::ZERO,
+            replay,
+            mock: None,
+        }
+    }
+
+    /// Frozen clock with scripted idle times
+    pub fn mock() -> Self {
+        info!("Serving mock timers");
+        Self {
+            mock: Some(Instant::now()),
+            ..Self::new(0.0, VecDeque::new())
+        }
+    }
+
+    fn advance(&mut self, delta: Duration) {
+        if let Some(start) = self.mock {
+            let step = start.elapsed().as_secs() / MOCK_IDLE_STEP.as_secs();
+            self.input_elapsed = MOCK_IDLE[step as usize % MOCK_IDLE.len()];
+            return;
+        }
+        self.time += delta;
+        self.input_elapsed += delta;
+        let mut replayed = false;
+        while let Some(offset) = self.replay.front() {
+            if *offset > self.time {
+                break;
+            }
+            self.input_elapsed = self.time - *offset;
+            self.replay.pop_front();
+            replayed = true;
+        }
+        if replayed && self.replay.is_empty() {
+            info!("Replay finished");
+        }
+    }
+}
+
+/// Notification that still has to be sent, by default that a timer went off
+struct PendingNotification {
+    /// Timer the notification is about, if any
+    timer: Option<String>,
+    /// Title of a notification about something else than a timer going off
+    title: Option<String>,
+    message: String,
+    /// Sent without waiting for a pause when the user is in flow
+    urgent: bool,
+    /// Only information that needs no action, sent with low urgency
+    info: bool,
+    since: Instant,
+    /// Longest time the notification is held back while the user is in flow
+    max_defer: Duration,
+}
+
+impl PendingNotification {
+    fn new(timer: &TimerConfig, message: String) -> Self {
+        Self {
+            timer: Some(timer.name.clone()),
+            title: None,
+            message,
+            urgent: false,
+            info: false,
+            since: Instant::now(),
+            max_defer: timer.max_defer.unwrap_or(MAX_DEFER),
+        }
+    }
+
+    /// Notification with its own title that isn't about a single timer
+    fn titled(title: String, message: String) -> Self {
+        Self {
+            timer: None,
+            title: Some(title),
+            message,
+            urgent: false,
+            info: false,
+            since: Instant::now(),
+            max_defer: MAX_DEFER,
+        }
+    }
+
+    /// Whether the notification can't be held back any longer
+    fn due(&self) -> bool {
+        self.urgent || self.since.elapsed() >= self.max_defer
+    }
+}
+
+struct State {
+    config: Config,
+    activity: Option<Box<dyn ActivityBackend>>,
+    activity_source: Option<ActivitySource>,
+    /// Last successful query of the activity source
+    activity_seen: Instant,
+    /// Whether the activity source stopped answering and this was logged
+    activity_stale: bool,
+    /// Whether the session is used remotely, which doesn't change while it lasts
+    remote_session: bool,
+    presence: Option<PresenceHook>,
+    /// Only checked when a timer uses it
+    audio: Option<AudioMonitor>,
+    window: Option<WindowTracker>,
+    focused: Option<FocusedWindow>,
+    #[cfg(feature = "border")]
+    border: Option<Border>,
+    simulation: Option<Simulation>,
+    timers: Vec<TimerState>,
+    last_update: Instant,
+    /// Boot time of the last update, which unlike the instant includes suspends
+    last_boot_time: Option<Duration>,
+    /// Whether the clocks are paused because of inactivity
+    idle: bool,
+    /// Whether the timers were reset because of inactivity since the last input
+    inactivity_reset: bool,
+    /// Last wake from a suspend that didn't reset the timers
+    resumed: Option<Instant>,
+    /// Start of the current stretch of intense typing
+    typing_since: Option<Instant>,
+    /// Whether the user is in flow, which holds back the notifications until a pause
+    in_flow: bool,
+    /// Notifications that still have to be sent
+    pending_notifications: Vec<PendingNotification>,
+    last_notification: Option<Instant>,
+    /// Time of the next attempt and the current backoff after notifications failed to be sent
+    notification_retry: Option<(Instant, Duration)>,
+    quiet_periods: Option<QuietPeriods>,
+    events: Option<EventLog>,
+    /// New names of configured timers by their configured name
+    renames: BTreeMap<String, String>,
+    /// Path to save the persistent state to, not set when simulating
+    state_path: Option<PathBuf>,
+    /// Path of the activity history, not set when simulating
+    history_path: Option<PathBuf>,
+    /// Start of the current period of activity
+    active_since: Option<SystemTime>,
+    last_prune: Option<Instant>,
+    /// Day of the last notification about the goals
+    goals_summarized: Option<NaiveDate>,
+    last_save: Instant,
+    /// Time of the last local input
+    last_input: Option<SystemTime>,
+    /// Time of the last input on any of the synchronized machines
+    shared_input: Option<SystemTime>,
+    /// Time since the last input that the timers saw at the last update and when it was measured
+    inactive: Option<(Instant, Duration)>,
+    /// Recent changes by clients, at most as many as configured
+    audit: VecDeque<AuditEntry>,
+    last_sync: Option<Instant>,
+    /// Start of the blink phase shared by all clients
+    blink_epoch: Instant,
+    /// Time of the last reset by the user and the timers from before it
+    undo: Option<(Instant, Vec<ResetUndo>)>,
+    undo_window: Duration,
+    /// Publishers of the changes to the subscribed clients of each socket
+    publishers: Vec<Publisher>,
+    /// Whether each timer went off, its clock and status as last published
+    published: BTreeMap<String, (bool, Duration, TimerStatus)>,
+}
+
+impl State {
+    fn init(
+        config: Config,
+        simulation: Option<Simulation>,
+        with_activity: bool,
+        undo_window: Duration,
+    ) -> Result<Self> {
+        crate::i18n::init(&config.language)?;
+        let state_path = if simulation.is_none() {
+            Some(crate::state_path()?)
+        } else {
+            None
+        };
+        let history_path = if simulation.is_none() {
+            Some(crate::history_path()?)
+        } else {
+            None
+        };
+        let persistent = match &state_path {
+            Some(path) => PersistentState::load_or_default(path)?,
+            None => PersistentState::default(),
+        };
+        for (i, timer) in config.timers.iter().enumerate() {
+            if let Some(j) = config.timers[..i].iter().position(|t| t.name == timer.name) {
+                bail!(
+                    "Duplicate timer name {}: timer {} ({:?}, interval {}) and timer {} ({:?}, interval {})",
+                    timer.name,
+                    j + 1,
+                    config.timers[j].kind,
+                    format_duration(config.timers[j].interval),
+                    i + 1,
+                    timer.kind,
+                    format_duration(timer.interval),
+                );
+            }
+        }
+        let timers = config
+            .timers
+            .iter()
+            .map(|t| {
+                let mut t = t.clone();
+                if let Some(name) = persistent.renames.get(&t.name) {
+                    if config.timers.iter().any(|other| &other.name == name) {
+                        warn!("Ignored rename of timer {} to existing name {name}", t.name);
+                    } else {
+                        t.name = name.clone();
+                    }
+                }
+                let mut timer = TimerState::new(t.clone())?;
+                if matches!(t.kind, TimerType::Daily | TimerType::App) {
+                    let day = current_day(t.reset_at());
+                    if let Some(daily) = persistent.daily.get(&t.name) {
+                        if daily.day == day.to_string() {
+                            timer.clock = Duration::from_secs(daily.secs);
+                        }
+                    }
+                    timer.day = Some(day);
+                }
+                if simulation.as_ref().is_some_and(|s| s.mock.is_some()) {
+                    if let Some((_, _, _, clock, on_break)) =
+                        MOCK_TIMERS.iter().find(|m| m.0 == t.name)
+                    {
+                        timer.clock = Duration::from_secs(*clock);
+                        timer.on_break = on_break.map(Duration::from_secs);
+                    }
+                }
+                if let Some(counters) = persistent.today.get(&t.name) {
+                    let today = Local::now().date_naive();
+                    if counters.day == today.to_string() {
+                        timer.today = Some(today);
+                        timer.fired_today = counters.fired;
+                        timer.elapsed_today = Duration::from_secs(counters.secs);
+                    }
+                }
+                Ok(timer)
+            })
+            .collect::<Result<Vec<TimerState>>>()?;
+        let (activity_source, activity) = match &config.activity {
+            Some(_) if simulation.is_none() && with_activity => (
+                Some(ActivitySource::Evdev),
+                Some(crate::activity::listen()?),
+            ),
+            Some(activity) if simulation.is_none() => {
+                let intensity =
+                    config.timers.iter().any(|t| t.adaptive) || config.notifications.flow.is_some();
+                let (source, backend) = crate::activity::connect(&activity.source, intensity)?;
+                (Some(source), Some(backend))
+            }
+            _ => (None, None),
+        };
+        let presence = config.activity.as_ref().and_then(|a| {
+            a.presence_command
+                .clone()
+                .map(|cmd| PresenceHook::new(cmd, a.presence_interval))
+        });
+        let window = match WindowTracker::connect() {
+            Ok(tracker) => Some(tracker),
+            Err(e) => {
+                if timers.iter().any(|t| t.config.kind == TimerType::App) {
+                    warn!("Window tracking unavailable, app timers won't count: {e:#}");
+                } else {
+                    info!("Window tracking unavailable: {e:#}");
+                }
+                None
+            }
+        };
+        let border = config.timers.iter().any(|t| t.border);
+        #[cfg(feature = "border")]
+        let border = border
+            .then(|| {
+                Border::spawn()
+                    .inspect_err(|e| warn!("Overdue border unavailable: {e:#}"))
+                    .ok()
+            })
+            .flatten();
+        #[cfg(not(feature = "border"))]
+        if border {
+            warn!("A timer has a border, but moved was built without the border feature");
+        }
+        let quiet_periods = QuietPeriods::new(
+            config.notifications.calendar.clone(),
+            config.notifications.quiet_command.clone(),
+        );
+        let events = match (&config.logging.events, &simulation) {
+            (Some(path), None) => Some(EventLog::open(
+                path,
+                config.logging.rotation.unwrap_or_default(),
+            )?),
+            _ => None,
+        };
+        // Don't notify when the daemon starts after the time of the summary
+        let now = Local::now();
+        let goals_summarized = config.stats.goal_summary.and_then(|at| {
+            (Duration::from_secs(now.num_seconds_from_midnight().into()) >= at)
+                .then(|| now.date_naive())
+        });
+        Ok(Self {
+            config,
+            activity,
+            activity_source,
+            activity_seen: Instant::now(),
+            activity_stale: false,
+            remote_session: crate::activity::remote_session(),
+            presence,
+            audio: timers
+                .iter()
+                .any(|t| t.config.audio.is_some())
+                .then(AudioMonitor::start),
+            window,
+            focused: None,
+            #[cfg(feature = "border")]
+            border,
+            simulation,
+            timers,
+            last_update: Instant::now(),
+            last_boot_time: None,
+            idle: false,
+            inactivity_reset: false,
+            resumed: None,
+            typing_since: None,
+            in_flow: false,
+            pending_notifications: Vec::new(),
+            last_notification: None,
+            notification_retry: None,
+            quiet_periods,
+            events,
+            renames: persistent.renames,
+            state_path,
+            history_path,
+            active_since: None,
+            last_prune: None,
+            goals_summarized,
+            last_save: Instant::now(),
+            last_input: None,
+            shared_input: None,
+            inactive: None,
+            audit: VecDeque::new(),
+            last_sync: None,
+            blink_epoch: Instant::now(),
+            undo: None,
+            undo_window,
+            publishers: Vec::new(),
+            published: BTreeMap::new(),
+        })
+    }
+
+    /// Sends the timers that went off, were reset, paused or resumed since the last call to the
+    /// subscribed clients, with `exact` any change of a clock counts, e.g. after messages
+    fn publish_changes(&mut self, exact: bool) {
+        if self.publishers.is_empty() {
+            return;
+        }
+        let mut changed = Vec::new();
+        for timer in &self.timers {
+            let now = (timer.went_off, timer.clock, timer.status());
+            let before = self.published.insert(timer.config.name.clone(), now);
+            let is_changed = before.is_none_or(|(went_off, clock, status)| {
+                went_off != now.0 || status != now.2 || now.1 < clock || (exact && now.1 != clock)
+            });
+            if is_changed {
+                changed.push((timer.config.name.clone(), self.info(timer)));
+            }
+        }
+        let timers = &self.timers;
+        self.published
+            .retain(|name, _| timers.iter().any(|t| t.config.name == *name));
+        if changed.is_empty() {
+            return;
+        }
+        let msg = match Response::Changed(changed).encode() {
+            Ok(msg) => msg,
+            Err(e) => return error!("Failed to encode the changes: {e:#}"),
+        };
+        for publisher in &self.publishers {
+            if let Err(e) = publisher.publish(msg.clone()) {
+                error!("Failed to publish the changes: {e:#}");
+            }
+        }
+    }
+
+    /// Resets the selected timers by the user, keeping their clocks for an undo
+    ///
+    /// Timers that can't be reset by the user are skipped.
+    fn user_reset(&mut self, selected: impl Fn(&TimerState) -> bool) {
+        let mut previous = Vec::new();
+        for timer in self
+            .timers
+            .iter_mut()
+            .filter(|t| selected(t) && t.config.reset_by.manual())
+        {
+            previous.push(ResetUndo::of(timer));
+            timer.reset();
+            log_event(
+                &mut self.events,
+                Event::Reset {
+                    timer: &timer.config.name,
+                    reason: ResetReason::User,
+                },
+            );
+        }
+        // A reset of nothing keeps the undo of the previous one
+        if !previous.is_empty() {
+            self.undo = Some((Instant::now(), previous));
+        }
+    }
+
+    /// Information of a timer including the deferral of its notification
+    fn info(&self, timer: &TimerState) -> TimerInfo {
+        let deferred = self
+            .pending_notifications
+            .iter()
+            .find(|n| self.in_flow && n.timer.as_ref() == Some(&timer.config.name))
+            .map(|n| (n.since.elapsed(), n.max_defer));
+        TimerInfo {
+            deferred,
+            blink: self.blink_on(),
+            ..timer.info()
+        }
+    }
+
+    /// Whether blinking bars are shown filled in the current phase
+    fn blink_on(&self) -> bool {
+        let rate = self.config.bar.blink_rate;
+        if rate <= 0.0 {
+            return true;
+        }
+        ((self.blink_epoch.elapsed().as_secs_f64() * rate) as u64).is_multiple_of(2)
+    }
+
+    /// Finds a timer by its name or an unambiguous prefix of it, otherwise suggests similar names
+    fn find_timer(&self, name: &str) -> Result<usize, ResponseError> {
+        if let Some(i) = self.timers.iter().position(|t| t.config.name == name) {
+            return Ok(i);
+        }
+        let prefixed: Vec<usize> = (0..self.timers.len())
+            .filter(|i| self.timers[*i].config.name.starts_with(name))
+            .collect();
+        if let [i] = prefixed.as_slice() {
+            return Ok(*i);
+        }
+        let similar: Vec<String> = self
+            .timers
+            .iter()
+            .map(|t| &t.config.name)
+            .filter(|n| n.starts_with(name) || edit_distance(n, name) <= SIMILAR_DISTANCE)
+            .cloned()
+            .collect();
+        if similar.is_empty() {
+            Err(ResponseError::NotFound)
+        } else {
+            Err(ResponseError::Similar(similar))
+        }
+    }
+
+    /// Logs a change by a client and keeps it for the status if configured
+    fn audit(&mut self, command: &Message, peer: Peer) {
+        self.record_audit(command, peer, peer.pid.and_then(process_name));
+    }
+
+    /// Records a command of a client that is described by `process` instead of its process ID
+    fn record_audit(&mut self, command: &Message, peer: Peer, process: Option<String>) {
+        info!(
+            "{command:?} from {peer} ({})",
+            process.as_deref().unwrap_or("unknown process")
+        );
+        if self.config.logging.audit == 0 {
+            return;
+        }
+        if self.audit.len() >= self.config.logging.audit {
+            self.audit.pop_front();
+        }
+        self.audit.push_back(AuditEntry {
+            time: unix_time().as_secs(),
+            message: format!("{command:?}"),
+            uid: peer.uid,
+            pid: peer.pid,
+            process,
+        });
+    }
+
+    fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.state_path else {
+            return Ok(());
+        };
+        let state = PersistentState {
+            daily: self
+                .timers
+                .iter()
+                .filter_map(|t| {
+                    t.day.map(|day| {
+                        (
+                            t.config.name.clone(),
+                            DailyClock {
+                                day: day.to_string(),
+                                secs: t.clock.as_secs(),
+                            },
+                        )
+                    })
+                })
+                .collect(),
+            renames: self.renames.clone(),
+            today: self
+                .timers
+                .iter()
+                .filter_map(|t| {
+                    t.today.map(|day| {
+                        (
+                            t.config.name.clone(),
+                            DayCounters {
+                                day: day.to_string(),
+                                fired: t.fired_today,
+                                secs: t.elapsed_today.as_secs(),
+                            },
+                        )
+                    })
+                })
+                .collect(),
+        };
+        state.save(path)?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    /// Ends the current period of activity and adds it to the history
+    fn end_activity(&mut self, end: SystemTime) {
+        let Some(start) = self.active_since.take() else {
+            return;
+        };
+        if let Some(path) = &self.history_path {
+            let period = Period {
+                start,
+                end: end.max(start),
+            };
+            if let Err(e) = crate::history::append(path, &period) {
+                error!("Failed to record activity: {e:#}");
+            }
+        }
+    }
+
+    /// Removes the history beyond the retention
+    fn prune_history(&mut self) -> Result<()> {
+        let (Some(path), Some(days)) = (&self.history_path, self.config.stats.retention) else {
+            return Ok(());
+        };
+        if self
+            .last_prune
+            .is_some_and(|t| t.elapsed() < PRUNE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_prune = Some(Instant::now());
+        let before = SystemTime::now() - Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+        let removed = crate::history::prune(path, before)?;
+        if removed > 0 {
+            info!("Pruned {removed} periods from the history");
+        }
+        Ok(())
+    }
+
+    /// Notifies how the goals went once their summary time passed
+    fn summarize_goals(&mut self) -> Result<()> {
+        let (Some(path), Some(at)) = (&self.history_path, self.config.stats.goal_summary) else {
+            return Ok(());
+        };
+        let now = Local::now();
+        let time = Duration::from_secs(now.num_seconds_from_midnight().into());
+        if self.config.stats.goals.is_empty()
+            || time < at
+            || self.goals_summarized == Some(now.date_naive())
+        {
+            return Ok(());
+        }
+        self.goals_summarized = Some(now.date_naive());
+        let mut periods = crate::history::read(path)?;
+        if let Some(start) = self.active_since {
+            periods.push(Period {
+                start,
+                end: SystemTime::now(),
+            });
+        }
+        let progress = self
+            .config
+            .stats
+            .goals
+            .iter()
+            .map(|g| {
+                crate::goals::evaluate(
+                    g,
+                    &periods,
+                    self.config.stats.break_length,
+                    now,
+                    &Formatter::new(&self.config.format),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let met = progress.iter().filter(|p| p.met).count();
+        info!("Met {met} of {} goals", progress.len());
+        let lines: Vec<String> = progress.iter().map(|p| p.to_string()).collect();
+        self.pending_notifications.push(PendingNotification {
+            info: true,
+            ..PendingNotification::titled(
+                crate::tr!("goals-met", met = met, total = progress.len()),
+                lines.join("\n"),
+            )
+        });
+        Ok(())
+    }
+
+    fn sync_due(&self) -> bool {
+        let Some(sync) = &self.config.sync else {
+            return false;
+        };
+        self.simulation.is_none()
+            && self
+                .last_sync
+                .is_none_or(|t| t.elapsed() >= sync.interval.unwrap_or(SYNC_INTERVAL))
+    }
+
+    /// Merges the timers with the sync file, the most recent reset and else the highest clock wins
+    fn sync(&mut self) -> Result<()> {
+        let Some(sync) = &self.config.sync else {
+            return Ok(());
+        };
+        self.last_sync = Some(Instant::now());
+        let mut shared = SharedState::load_or_default(&sync.file)?;
+        for timer in self.timers.iter_mut() {
+            if timer.config.kind != TimerType::Break {
+                continue;
+            }
+            if let Some(clock) = shared.timers.get(&timer.config.name) {
+                let elapsed = Duration::from_secs(clock.elapsed_secs);
+                if clock.reset > timer.reset_epoch && timer.config.reset_by == ResetSource::Never {
+                    // Adopt the epoch, so the newer clock of either machine is kept from now on
+                    timer.clock = timer.clock.max(elapsed);
+                    timer.reset_epoch = clock.reset;
+                } else if clock.reset > timer.reset_epoch {
+                    info!("Timer {} was reset on another machine", timer.config.name);
+                    timer.reset();
+                    timer.clock = elapsed;
+                    timer.reset_epoch = clock.reset;
+                } else if clock.reset == timer.reset_epoch {
+                    timer.clock = timer.clock.max(elapsed);
+                }
+            }
+            shared.timers.insert(
+                timer.config.name.clone(),
+                SharedClock {
+                    elapsed_secs: timer.clock.as_secs(),
+                    reset: timer.reset_epoch,
+                },
+            );
+        }
+        if let Some(last_input) = self.last_input {
+            let millis = last_input
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            shared.last_input = shared.last_input.max(millis);
+        }
+        self.shared_input = Some(SystemTime::UNIX_EPOCH + Duration::from_millis(shared.last_input));
+        shared.save(&sync.file)
+    }
+}
+
+pub struct Daemon {
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    socket_threads: Vec<JoinHandle<()>>,
+    socket_wakers: Vec<Arc<Waker>>,
+    /// Lock of the single instance, held until the daemon exits
+    _lock: Option<File>,
+}
+
+/// Locks the file and writes the process ID to it, fails if another daemon holds the lock
+fn lock_instance(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create runtime directory {dir:?}"))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {path:?}"))?;
+    // SAFETY: the descriptor stays valid while the file is open, the lock is released with it
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::WouldBlock {
+            return Err(e).with_context(|| format!("Failed to lock {path:?}"));
+        }
+        let mut pid = String::new();
+        file.read_to_string(&mut pid)?;
+        bail!(
+            "{} is already running (pid {})",
+            crate::DAEMON_NAME,
+            pid.trim()
+        );
+    }
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    Ok(file)
+}
+
+impl Daemon {
+    /// Starts serving the timers on the daemon socket, stops on SIGINT and SIGTERM
+    ///
+    /// Fails if another daemon of the user is already running.
+    pub fn start(
+        config: Config,
+        simulation: Option<Simulation>,
+        with_activity: bool,
+        undo_window: Duration,
+    ) -> Result<Self> {
+        let lock = lock_instance(&crate::daemon_pid_file()?)?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGINT, shutdown.clone())?;
+        signal_hook::flag::register(signal_hook::consts::SIGTERM, shutdown.clone())?;
+        crate::logging::reopen_on_signal()?;
+        Ok(Self {
+            _lock: Some(lock),
+            ..Self::serve(
+                config,
+                simulation,
+                with_activity,
+                undo_window,
+                crate::daemon_socket()?,
+                shutdown,
+            )?
+        })
+    }
+
+    /// Serves the timers on the given socket without installing signal handlers
+    pub(crate) fn serve(
+        config: Config,
+        simulation: Option<Simulation>,
+        with_activity: bool,
+        undo_window: Duration,
+        socket: PathBuf,
+        shutdown: Arc<AtomicBool>,
+    ) -> Result<Self> {
+        let datagram_socket = socket.with_extension("dgram.sock");
+        let control_socket = socket.with_extension("control.sock");
+        let control_token = socket.with_extension("control.token");
+        let read_only = config.read_only;
+        let remote = config.remote.clone();
+        let dashboard = config.dashboard.clone();
+        let datagram = config.datagram;
+        let state = Arc::new(Mutex::new(State::init(
+            config,
+            simulation,
+            with_activity,
+            undo_window,
+        )?));
+
+        let mut sockets = if read_only {
+            vec![
+                (SocketServer::create(socket, false)?, Access::ReadOnly),
+                (
+                    SocketServer::create(control_socket, false)?,
+                    Access::Token {
+                        token: write_control_token(&control_token)?,
+                        full: true,
+                    },
+                ),
+            ]
+        } else {
+            vec![(SocketServer::create(socket, false)?, Access::Full)]
+        };
+        if let Some(remote) = remote {
+            sockets.push((
+                SocketServer::bind_tcp(remote.listen)?,
+                Access::Token {
+                    token: remote.token,
+                    full: false,
+                },
+            ));
+        }
+        let socket_wakers = sockets.iter().map(|(socket, _)| socket.waker()).collect();
+        state.lock().publishers = sockets
+            .iter()
+            .map(|(socket, _)| socket.publisher())
+            .collect();
+        let mut socket_threads: Vec<_> = sockets
+            .into_iter()
+            .map(|(socket, access)| {
+                Self::start_socket(socket, access, shutdown.clone(), state.clone())
+            })
+            .collect();
+        if datagram {
+            socket_threads.push(Self::start_datagram(
+                DatagramServer::create(datagram_socket)?,
+                shutdown.clone(),
+                state.clone(),
+            ));
+        }
+        if let Some(dashboard) = dashboard {
+            #[cfg(feature = "dashboard")]
+            socket_threads.push(Self::start_dashboard(
+                Dashboard::bind(&dashboard)?,
+                shutdown.clone(),
+                state.clone(),
+            ));
+            #[cfg(not(feature = "dashboard"))]
+            warn!(
+                "Dashboard on {} is configured, but moved was built without the dashboard feature",
+                dashboard.listen
+            );
+        }
+
+        Ok(Self {
+            shutdown,
+            state,
+            socket_threads,
+            socket_wakers,
+            _lock: None,
+        })
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        while !self.shutdown.load(Ordering::Relaxed) {
+            {
+                let mut state = self.state.lock();
+                Self::update(&mut state)?;
+                if state.last_save.elapsed() >= SAVE_INTERVAL {
+                    if let Err(e) = state.save() {
+                        error!("Failed to save state: {e}");
+                    }
+                }
+                if let Err(e) = state.prune_history() {
+                    error!("Failed to prune history: {e:#}");
+                }
+                if let Err(e) = state.summarize_goals() {
+                    error!("Failed to summarize goals: {e:#}");
+                }
+                if state.sync_due() {
+                    if let Err(e) = state.sync() {
+                        error!("Failed to synchronize timers: {e:#}");
+                    }
+                }
+            }
+            thread::sleep(HEARTBEAT);
+        }
+        info!("Shutting down");
+        let mut state = self.state.lock();
+        state.end_activity(SystemTime::now());
+        for led in state.timers.iter_mut().filter_map(|t| t.led.as_mut()) {
+            let _ = led.restore();
+        }
+        state.save()?;
+        drop(state);
+        self.join_sockets()
+    }
+
+    /// Advances the simulated clock in heartbeats and updates the timers after each of them
+    #[cfg(feature = "test-util")]
+    pub(crate) fn advance(&self, mut delta: Duration) -> Result<()> {
+        let mut state = self.state.lock();
+        while !delta.is_zero() {
+            let step = delta.min(HEARTBEAT);
+            let Some(simulation) = &mut state.simulation else {
+                bail!("The clock can only be advanced while simulating");
+            };
+            simulation.pending += step;
+            Self::update(&mut state)?;
+            delta -= step;
+        }
+        Ok(())
+    }
+
+    /// Synchronizes the timers with the other machines, which isn't done by itself while the
+    /// clock is simulated
+    #[cfg(feature = "test-util")]
+    pub(crate) fn sync(&self) -> Result<()> {
+        self.state.lock().sync()
+    }
+
+    /// Sets the simulated time since the last input
+    #[cfg(feature = "test-util")]
+    pub(crate) fn set_idle(&self, idle: Duration) {
+        if let Some(simulation) = &mut self.state.lock().simulation {
+            simulation.input_elapsed = idle;
+        }
+    }
+
+    /// Stops serving without saving anything
+    #[cfg(feature = "test-util")]
+    pub(crate) fn stop(&mut self) -> Result<()> {
+        self.shutdown.store(true, Ordering::Relaxed);
+        self.join_sockets()
+    }
+
+    /// Wakes the socket threads so they notice the shutdown and waits for them
+    fn join_sockets(&mut self) -> Result<()> {
+        for waker in &self.socket_wakers {
+            waker.wake()?;
+        }
+        for socket_thread in self.socket_threads.drain(..) {
+            let _ = socket_thread.join();
+        }
+        Ok(())
+    }
+
+    /// Serves the socket in a new thread with the given access for its clients
+    fn start_socket(
+        mut socket: SocketServer,
+        access: Access,
+        shutdown: Arc<AtomicBool>,
+        state: Arc<Mutex<State>>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            socket
+                .serve_until(shutdown.clone(), |msg, peer| match Self::handle_connection(
+                    state.clone(),
+                    &shutdown,
+                    &access,
+                    msg,
+                    peer,
+                ) {
+                    Ok(answer) => answer,
+                    Err(e) => {
+                        error!("Failed to handle connection: {e}");
+                        Answer::Respond(None)
+                    }
+                })
+                .unwrap();
+        })
+    }
+
+    /// Serves read-only queries on the datagram socket in a new thread
+    fn start_datagram(
+        mut socket: DatagramServer,
+        shutdown: Arc<AtomicBool>,
+        state: Arc<Mutex<State>>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let result = socket.serve_until(shutdown.clone(), |msg| {
+                let response = Message::decode(msg).and_then(|msg| {
+                    if msg.is_read_only() {
+                        Self::handle_message(&mut state.lock(), &shutdown, msg)
+                    } else {
+                        Ok(Response::Error(ResponseError::ReadOnly))
+                    }
+                });
+                match response.and_then(|mut r| {
+                    hide_audit(&mut r);
+                    r.encode()
+                }) {
+                    Ok(resp) => Some(resp),
+                    Err(e) => {
+                        error!("Failed to handle datagram: {e}");
+                        None
+                    }
+                }
+            });
+            if let Err(e) = result {
+                error!("Datagram socket failed: {e:#}");
+            }
+        })
+    }
+
+    /// Serves the dashboard in a new thread, it stops with the daemon
+    #[cfg(feature = "dashboard")]
+    fn start_dashboard(
+        dashboard: Dashboard,
+        shutdown: Arc<AtomicBool>,
+        state: Arc<Mutex<State>>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            if let Err(e) = dashboard.serve_until(shutdown, |request| {
+                Self::handle_dashboard(&mut state.lock(), request)
+            }) {
+                error!("Dashboard failed: {e:#}");
+            }
+        })
+    }
+
+    #[cfg(feature = "dashboard")]
+    fn handle_dashboard(state: &mut State, request: Request) -> Reply {
+        match request {
+            Request::Overview => Reply::Overview(Overview {
+                idle_secs: state
+                    .last_input
+                    .and_then(|t| t.elapsed().ok())
+                    .map(|d| d.as_secs()),
+                timers: state.timers.iter().map(Self::timer_overview).collect(),
+            }),
+            Request::Timer(name) => state
+                .timers
+                .iter()
+                .find(|t| t.config.name == name)
+                .map(|t| Reply::Timer(Self::timer_overview(t)))
+                .unwrap_or(Reply::NotFound),
+            Request::Reset(_, addr) if state.config.read_only => {
+                info!("Refused reset over HTTP from {addr:?} in read-only mode");
+                Reply::Forbidden
+            }
+            Request::Reset(name, addr) => match state.find_timer(&name) {
+                Ok(i) if !state.timers[i].config.reset_by.manual() => Reply::Forbidden,
+                Ok(i) => {
+                    let name = state.timers[i].config.name.clone();
+                    let client = match addr {
+                        Some(addr) => format!("dashboard client {addr}"),
+                        None => "dashboard client".to_string(),
+                    };
+                    state.record_audit(
+                        &Message::Reset(name.clone()),
+                        Peer::default(),
+                        Some(client),
+                    );
+                    state.user_reset(|t| t.config.name == name);
+                    state.publish_changes(true);
+                    Reply::Ok
+                }
+                Err(_) => Reply::NotFound,
+            },
+        }
+    }
+
+    #[cfg(feature = "dashboard")]
+    fn timer_overview(timer: &TimerState) -> TimerOverview {
+        let info = timer.info();
+        TimerOverview {
+            name: timer.config.name.clone(),
+            elapsed_secs: info.elapsed.as_secs(),
+            interval_secs: info.interval.as_secs(),
+            status: info.status.to_string(),
+            overdue: info.overdue,
+        }
+    }
+
+    fn update(state: &mut State) -> Result<()> {
+        let real_delta = state.last_update.elapsed();
+        let delta = match &mut state.simulation {
+            Some(simulation) => {
+                let delta =
+                    real_delta.mul_f64(simulation.speed) + std::mem::take(&mut simulation.pending);
+                simulation.advance(delta);
+                delta
+            }
+            None => real_delta,
+        };
+
+        // The monotonic clock stops during a suspend, so the boot time advances further
+        let on_suspend = state
+            .config
+            .activity
+            .as_ref()
+            .map_or(SuspendPolicy::Continue, |a| a.on_suspend);
+        let boot_time = crate::activity::boot_time()
+            .inspect_err(|e| warn!("Failed to read the boot time: {e:#}"))
+            .ok();
+        let suspend = match (boot_time, state.last_boot_time) {
+            (Some(now), Some(last)) => now.saturating_sub(last).saturating_sub(real_delta),
+            _ => Duration::ZERO,
+        };
+        state.last_boot_time = boot_time;
+        let suspended = suspend >= SUSPEND_GAP;
+        if suspended {
+            info!(
+                "Resumed after a suspend of {}, timers {}",
+                format_duration(suspend),
+                match on_suspend {
+                    SuspendPolicy::Reset => "are reset",
+                    SuspendPolicy::Pause => "stay paused",
+                    SuspendPolicy::Continue => "count the suspend",
+                }
+            );
+            if on_suspend != SuspendPolicy::Reset {
+                state.resumed = Some(Instant::now());
+            }
+        }
+        let delta = if suspended && on_suspend == SuspendPolicy::Continue {
+            delta + suspend
+        } else {
+            delta
+        };
+
+        let (mut input_elapsed, intensity) = if state.config.activity.is_none() {
+            (None, None)
+        } else if let Some(simulation) = &state.simulation {
+            (Some(simulation.input_elapsed), None)
+        } else if let Some(backend) = &mut state.activity {
+            match backend.query() {
+                Ok(activity) => {
+                    if state.activity_stale {
+                        info!("Activity source is answering again");
+                        state.activity_stale = false;
+                    }
+                    state.activity_seen = Instant::now();
+                    (Some(activity.input_elapsed), activity.intensity)
+                }
+                // Without activity the timers keep running, which is only noticed with a warning
+                Err(e) => {
+                    if !state.activity_stale && state.activity_seen.elapsed() >= ACTIVITY_STALE {
+                        warn!(
+                            "No answer from the activity source for {}, timers won't pause or reset on inactivity: {e:#}",
+                            format_duration(state.activity_seen.elapsed())
+                        );
+                        state.activity_stale = true;
+                    }
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+
+        // The user counts as active while the presence command reports them present
+        if let (Some(_), Some(presence)) = (input_elapsed, &mut state.presence) {
+            if presence.present() {
+                input_elapsed = Some(Duration::ZERO);
+            }
+        }
+
+        // Input on the other synchronized machines counts as activity as well, a simulation only
+        // has its own input
+        if let Some(elapsed) = input_elapsed {
+            state.last_input = SystemTime::now().checked_sub(elapsed);
+            if let (Some(shared), None) = (state.shared_input, &state.simulation) {
+                input_elapsed = Some(elapsed.min(shared.elapsed().unwrap_or_default()));
+            }
+        }
+
+        // The time spent suspended only counts as inactivity when the timers reset on suspend
+        if let (Some(elapsed), Some(resumed)) = (input_elapsed, state.resumed) {
+            input_elapsed = Some(elapsed.min(resumed.elapsed()));
+        }
+        // A pause ended when the time since the last input went back, its length is the time
+        // that was reported before
+        let ended_pause = match (state.inactive, input_elapsed) {
+            (Some((_, before)), Some(elapsed)) if elapsed < before => Some(before),
+            _ => None,
+        };
+        state.inactive = input_elapsed.map(|elapsed| (Instant::now(), elapsed));
+
+        let mut reset = false;
+
+        let (inactivity_pause, inactivity_reset) = if let Some(activity) = &state.config.activity {
+            (activity.inactivity_pause, activity.inactivity_reset)
+        } else {
+            (None, None)
+        };
+
+        // Speed factor for adaptive timers, heavy typing makes the clock run faster
+        let intensity_factor = match (intensity, &state.config.activity) {
+            (Some(intensity), Some(config)) if config.intensity_baseline > 0.0 => {
+                (intensity / config.intensity_baseline).clamp(ADAPTIVE_MIN, ADAPTIVE_MAX)
+            }
+            _ => 1.0,
+        };
+        let idle = inactivity_pause.is_some() && input_elapsed > inactivity_pause;
+        if idle != state.idle {
+            let event = if idle { Event::Idle } else { Event::Active };
+            log_event(&mut state.events, event);
+        }
+        state.idle = idle;
+
+        // The user is in flow after typing intensely for a while, until the next pause
+        if let Some(flow) = &state.config.notifications.flow {
+            if intensity.is_some_and(|i| i >= flow.intensity) {
+                state.typing_since.get_or_insert_with(Instant::now);
+            } else {
+                state.typing_since = None;
+            }
+            let in_flow = state
+                .typing_since
+                .is_some_and(|t| t.elapsed() >= flow.after)
+                && input_elapsed.is_some_and(|e| e < flow.pause);
+            if in_flow != state.in_flow {
+                if in_flow {
+                    info!("User is in flow, holding back notifications");
+                } else {
+                    info!("Pause in typing, sending held back notifications");
+                }
+                state.in_flow = in_flow;
+            }
+        }
+
+        // Record periods of activity, which end at the last input
+        if state.idle {
+            let last_input = SystemTime::now() - input_elapsed.unwrap_or_default();
+            state.end_activity(last_input);
+        } else if state.active_since.is_none() {
+            state.active_since = Some(SystemTime::now());
+        }
+
+        if let Some(window) = &mut state.window {
+            state.focused = window.focused().unwrap_or_else(|e| {
+                warn!("Failed to get focused window: {e:#}");
+                None
+            });
+        }
+
+        let audio_playing = state.audio.as_ref().is_some_and(AudioMonitor::playing);
+
+        // Reset when inactive or after a suspend
+        if (inactivity_reset.is_some() && input_elapsed >= inactivity_reset)
+            || (suspended && on_suspend == SuspendPolicy::Reset)
+        {
+            reset = true;
+        }
+
+        if reset {
+            if !state.inactivity_reset {
+                info!("Resetting timers because of inactivity");
+                state.inactivity_reset = true;
+                log_event(&mut state.events, Event::InactivityReset);
+            }
+        } else if state.inactivity_reset && !state.idle {
+            state.inactivity_reset = false;
+            info!("Activity resumed after the timers were reset");
+            if state.config.notifications.welcome_back {
+                state.pending_notifications.push(PendingNotification {
+                    info: true,
+                    ..PendingNotification::titled(
+                        crate::tr!("welcome-back"),
+                        crate::tr!("welcome-back-body"),
+                    )
+                });
+            }
+        }
+
+        // Clocks before the update, to count the progress of today
+        let before: Vec<(Duration, bool)> =
+            state.timers.iter().map(|t| (t.clock, t.went_off)).collect();
+        let fmt = Formatter::new(&state.config.format);
+        for timer in state.timers.iter_mut() {
+            trace!(
+                "Update {}, clock: {:?}, interval: {:?}",
+                timer.config.name,
+                timer.clock,
+                timer.config.interval
+            );
+            let pulse = timer.is_overdue() && timer.on_break.is_none();
+            if let Some(led) = &mut timer.led {
+                let result = if pulse { led.toggle() } else { led.restore() };
+                if let Err(e) = result {
+                    warn!("Failed to signal timer {}: {e:#}", timer.config.name);
+                }
+            }
+            if let (TimerType::Break, Some(reset_at)) = (timer.config.kind, timer.config.reset_at) {
+                let day = current_day(reset_at);
+                if timer.day.is_some_and(|d| d != day) {
+                    info!("Scheduled reset of timer {}", timer.config.name);
+                    timer.reset();
+                    log_event(
+                        &mut state.events,
+                        Event::Reset {
+                            timer: &timer.config.name,
+                            reason: ResetReason::Schedule,
+                        },
+                    );
+                }
+                timer.day = Some(day);
+            }
+            timer.paused = None;
+            match timer.config.kind {
+                TimerType::Daily => {
+                    timer.paused = state.idle.then_some(TimerStatus::PausedIdle);
+                    Self::update_daily(timer, delta, &mut state.pending_notifications, &fmt);
+                    continue;
+                }
+                TimerType::App => {
+                    let matches = state.focused.as_ref().is_some_and(|w| {
+                        timer
+                            .pattern
+                            .as_ref()
+                            .is_some_and(|p| p.is_match(&w.class) || p.is_match(&w.title))
+                    });
+                    timer.paused = if state.idle {
+                        Some(TimerStatus::PausedIdle)
+                    } else if !matches {
+                        Some(TimerStatus::PausedApp)
+                    } else {
+                        None
+                    };
+                    Self::update_daily(timer, delta, &mut state.pending_notifications, &fmt);
+                    continue;
+                }
+                TimerType::Reminder => {
+                    Self::update_reminder(timer, &mut state.pending_notifications, &fmt);
+                    continue;
+                }
+                TimerType::Countdown => {
+                    // Countdowns run regardless of activity
+                    timer.clock += delta;
+                    if timer.clock >= timer.config.interval {
+                        info!("Countdown {} finished", timer.config.name);
+                        if timer.config.speak {
+                            crate::speak(&crate::tr!(
+                                "spoken-countdown",
+                                name = timer.config.name.as_str()
+                            ));
+                        }
+                        state.pending_notifications.push(PendingNotification {
+                            urgent: true,
+                            ..PendingNotification::new(
+                                &timer.config,
+                                crate::tr!("countdown-finished"),
+                            )
+                        });
+                        timer.went_off = true;
+                    }
+                    continue;
+                }
+                TimerType::Break => {}
+            }
+
+            if let Some(remaining) = timer.on_break {
+                // Clock is paused during the break
+                if let Some(remaining) = remaining.checked_sub(delta) {
+                    timer.on_break = Some(remaining);
+                } else {
+                    info!("Break of timer {} complete", timer.config.name);
+                    state.pending_notifications.push(PendingNotification {
+                        title: Some(crate::tr!(
+                            "break-complete",
+                            name = timer.config.name.as_str()
+                        )),
+                        ..PendingNotification::new(&timer.config, crate::tr!("break-complete-body"))
+                    });
+                    if timer.config.speak {
+                        crate::speak(&crate::tr!(
+                            "spoken-break-over",
+                            name = timer.config.name.as_str()
+                        ));
+                    }
+                    // Breaks are refused on these timers, keep the clock should one end anyway
+                    if timer.config.reset_by.manual() {
+                        timer.reset();
+                        log_event(
+                            &mut state.events,
+                            Event::Reset {
+                                timer: &timer.config.name,
+                                reason: ResetReason::Break,
+                            },
+                        );
+                    } else {
+                        timer.on_break = None;
+                    }
+                }
+                continue;
+            }
+
+            let audio = if audio_playing {
+                timer.config.audio
+            } else {
+                None
+            };
+
+            let inactivity_reset = audio.is_none() && timer.config.reset_by.inactivity();
+            if inactivity_reset
+                && timer.config.duration.is_some()
+                && input_elapsed > timer.config.duration
+            {
+                // Rest if over break duration
+                timer.clock = Duration::ZERO;
+                reset = true;
+            }
+
+            if reset && inactivity_reset {
+                info!("Reset timer {}", timer.config.name);
+                timer.reset();
+                timer.paused = Some(TimerStatus::PausedIdle);
+                continue;
+            }
+
+            if let (Some(credit), Some(pause)) = (&timer.config.credit, ended_pause) {
+                let left = timer.config.interval.saturating_sub(timer.clock);
+                if inactivity_reset && pause >= credit.min_break && left <= credit.within {
+                    info!(
+                        "Counted the pause of {} as the break of timer {}",
+                        format_duration(pause),
+                        timer.config.name
+                    );
+                    timer.reset();
+                    log_event(
+                        &mut state.events,
+                        Event::Reset {
+                            timer: &timer.config.name,
+                            reason: ResetReason::Credit,
+                        },
+                    );
+                    continue;
+                }
+            }
+
+            let focused =
+                |classes: &[String]| state.focused.as_ref().is_some_and(|w| w.is_any(classes));
+            if focused(&timer.config.pause_in) {
+                timer.paused = Some(TimerStatus::PausedApp);
+                continue;
+            }
+            let active = match timer.config.count {
+                CountMode::WallClock => !state.idle,
+                CountMode::ActiveOnly => input_elapsed.is_none_or(|e| e <= ACTIVE_THRESHOLD),
+            };
+            if active || audio == Some(AudioPolicy::Activity) || focused(&timer.config.count_in) {
+                // Only update clock if not paused
+                if timer.config.adaptive {
+                    timer.clock += delta.mul_f64(intensity_factor);
+                } else {
+                    timer.clock += delta;
+                }
+            } else {
+                timer.paused = Some(TimerStatus::PausedIdle);
+            }
+
+            if !timer.went_off && timer.clock > timer.config.interval {
+                info!("Timer {} went off", timer.config.name);
+                if timer.config.speak {
+                    crate::speak(&crate::tr!(
+                        "spoken-break",
+                        worked = format_spoken(timer.clock)
+                    ));
+                }
+                if timer.config.notify {
+                    let suggestion = timer.suggestion();
+                    state
+                        .pending_notifications
+                        .push(PendingNotification::new(&timer.config, suggestion));
+                }
+                timer.went_off = true;
+            }
+
+            if let Some(overdue) = timer.clock.checked_sub(timer.config.interval) {
+                Self::nag(timer, overdue, &fmt);
+            }
+        }
+        let today = Local::now().date_naive();
+        for (timer, (clock, went_off)) in state.timers.iter_mut().zip(before) {
+            timer.count_today(today, clock, went_off);
+            if timer.went_off && !went_off {
+                log_event(
+                    &mut state.events,
+                    Event::Fired {
+                        timer: &timer.config.name,
+                    },
+                );
+            }
+        }
+        // Before finished countdowns are removed, so subscribers see them go off
+        state.publish_changes(false);
+        state
+            .timers
+            .retain(|t| !(t.config.kind == TimerType::Countdown && t.went_off));
+        #[cfg(feature = "border")]
+        if let Some(border) = &mut state.border {
+            let intensity = state
+                .timers
+                .iter()
+                .map(TimerState::border_intensity)
+                .fold(0.0, f32::max);
+            border.set_intensity(intensity);
+        }
+        Self::flush_notifications(state);
+        state.last_update = Instant::now();
+        Ok(())
+    }
+
+    /// Shows a nagbar once the timer is overdue by more than its nag time
+    fn nag(timer: &mut TimerState, overdue: Duration, fmt: &Formatter) {
+        let Some(nag_after) = timer.config.nag_after else {
+            return;
+        };
+        if timer.nagged || overdue <= nag_after {
+            return;
+        }
+        timer.nagged = true;
+        info!(
+            "Timer {} is badly overdue, showing nagbar",
+            timer.config.name
+        );
+        let message = crate::tr!(
+            "nag-overdue",
+            name = timer.config.name.as_str(),
+            overdue = fmt.duration(overdue)
+        );
+        match crate::nag::show(&timer.config.name, &message) {
+            Ok(nagbar) => timer.nagbar = Some(nagbar),
+            Err(e) => error!("Failed to show nagbar: {e:#}"),
+        }
+    }
+
+    /// Lets a reminder go off at its times of the day, its clock counts from the previous one
+    fn update_reminder(
+        timer: &mut TimerState,
+        notifications: &mut Vec<PendingNotification>,
+        fmt: &Formatter,
+    ) {
+        let now = Local::now();
+        let time = Duration::from_secs(now.num_seconds_from_midnight().into());
+        let passed = timer.config.at.iter().filter(|t| **t <= time).count();
+        if timer.day != Some(now.date_naive()) {
+            // Times that passed before the daemon started or the day began don't go off
+            timer.reset();
+            timer.day = Some(now.date_naive());
+            timer.thresholds_passed = passed;
+        }
+        if passed > timer.thresholds_passed {
+            info!("Reminder {} went off", timer.config.name);
+            if timer.config.speak {
+                crate::speak(&crate::tr!(
+                    "spoken-reminder",
+                    name = timer.config.name.as_str()
+                ));
+            }
+            if timer.config.notify {
+                let message = if timer.suggestions.is_empty() {
+                    crate::tr!("reminder-time", time = fmt.time(&now))
+                } else {
+                    timer.suggestion()
+                };
+                notifications.push(PendingNotification::new(&timer.config, message));
+            }
+            timer.went_off = true;
+            timer.dismiss_nagbar();
+        }
+        timer.thresholds_passed = passed;
+
+        let (previous, next) = reminder_bounds(&timer.config.at, time);
+        timer.clock = time - previous;
+        timer.span = Some(next - previous);
+        if timer.went_off {
+            Self::nag(timer, timer.clock, fmt);
+        }
+    }
+
+    /// Counts the time of a daily or app timer while it isn't paused
+    fn update_daily(
+        timer: &mut TimerState,
+        delta: Duration,
+        notifications: &mut Vec<PendingNotification>,
+        fmt: &Formatter,
+    ) {
+        let day = current_day(timer.config.reset_at());
+        if timer.day != Some(day) {
+            info!("New day, reset daily timer {}", timer.config.name);
+            timer.reset();
+            timer.day = Some(day);
+        }
+        if timer.paused.is_none() {
+            timer.clock += delta;
+        }
+
+        let passed = timer.passed_thresholds();
+        if passed > timer.thresholds_passed {
+            info!("Daily timer {} passed a threshold", timer.config.name);
+            if timer.config.notify {
+                let time = fmt.total(timer.clock);
+                let message = if timer.config.kind == TimerType::App {
+                    crate::tr!("daily-app", time = time, name = timer.config.name.as_str())
+                } else {
+                    crate::tr!("daily-active", time = time)
+                };
+                notifications.push(PendingNotification::new(&timer.config, message));
+            }
+            timer.thresholds_passed = passed;
+        }
+        timer.went_off = timer.clock > timer.config.interval;
+    }
+
+    /// Sends the pending notifications as one notification, respecting the minimum spacing
+    fn flush_notifications(state: &mut State) {
+        if state.pending_notifications.is_empty() {
+            return;
+        }
+        if let (Some(last), Some(spacing)) = (
+            state.last_notification,
+            state.config.notifications.min_spacing,
+        ) {
+            if last.elapsed() < spacing {
+                return;
+            }
+        }
+        // Notifications are sent once the quiet period is over
+        if state.quiet_periods.as_mut().is_some_and(|q| q.active()) {
+            return;
+        }
+        if state
+            .notification_retry
+            .is_some_and(|(at, _)| Instant::now() < at)
+        {
+            return;
+        }
+        // Notifications wait for a pause in typing while the user is in flow, up to their cap
+        if state.in_flow && !state.pending_notifications.iter().any(|n| n.due()) {
+            return;
+        }
+        let pending = &state.pending_notifications;
+        let names: Vec<&str> = pending
+            .iter()
+            .filter(|n| n.title.is_none())
+            .filter_map(|n| n.timer.as_deref())
+            .collect();
+        let title = match names.as_slice() {
+            [name] => crate::tr!("timer-went-off", name = *name),
+            [init @ .., last] => {
+                crate::tr!("timers-went-off", names = init.join(", "), last = *last)
+            }
+            // Only titled notifications, the first one gives the title
+            [] => pending[0].title.clone().unwrap_or_default(),
+        };
+        // The titles that didn't make it into the title of the notification lead their message
+        let body = pending
+            .iter()
+            .map(|n| match &n.title {
+                Some(own) if *own != title => format!("{own}: {}", n.message),
+                _ => n.message.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let result = if pending.iter().all(|n| n.info) {
+            crate::try_send_info_notification(&title, &body)
+        } else {
+            crate::try_send_notification(&title, &body)
+        };
+        // Keep the notifications when there is no notification daemon yet, e.g. at login
+        if let Err(e) = result {
+            let backoff = state
+                .notification_retry
+                .map_or(NOTIFY_RETRY_MIN, |(_, b)| (b * 2).min(NOTIFY_RETRY_MAX));
+            warn!("Notifications unavailable, retrying in {backoff:?}: {e:#}");
+            state.notification_retry = Some((Instant::now() + backoff, backoff));
+            return;
+        }
+        if state.notification_retry.take().is_some() {
+            info!("Notifications available again");
+        }
+        state.pending_notifications.clear();
+        state.last_notification = Some(Instant::now());
+    }
+
+    fn handle_connection(
+        state: Arc<Mutex<State>>,
+        shutdown: &AtomicBool,
+        access: &Access,
+        msg: &[u8],
+        peer: Peer,
+    ) -> Result<Answer> {
+        let Ok(command) = Message::decode(msg) else {
+            warn!("Received a message that can't be decoded from {peer}");
+            return Ok(Answer::Respond(Some(
+                Response::Error(ResponseError::UnknownMessage).encode()?,
+            )));
+        };
+        let (subscribe, changes) = (command.subscribes(), !command.is_read_only());
+        // Only connections can subscribe, the socket server then sends them the changes
+        let handle = |state: &mut State, msg: Message| {
+            if msg.subscribes() {
+                Ok(Response::Ok)
+            } else {
+                Self::handle_message(state, shutdown, msg)
+            }
+        };
+        let mut state = state.lock();
+        let mut full = matches!(access, Access::Full);
+        let mut response = match (access, command) {
+            (Access::Full, command) => {
+                if !command.is_read_only() {
+                    state.audit(&command, peer);
+                }
+                handle(&mut state, command)?
+            }
+            (Access::ReadOnly, command) if command.is_read_only() => handle(&mut state, command)?,
+            (Access::ReadOnly, command) => {
+                info!(
+                    "Refused {} from {peer} on the read-only socket",
+                    command.name()
+                );
+                Response::Error(ResponseError::ReadOnly)
+            }
+            (Access::Token { token, full: true }, Message::Authenticated(given, msg))
+                if tokens_equal(&given, token) =>
+            {
+                if !msg.is_read_only() {
+                    state.audit(&msg, peer);
+                }
+                full = true;
+                handle(&mut state, *msg)?
+            }
+            (Access::Token { full: true, .. }, command) if command.is_read_only() => {
+                handle(&mut state, command)?
+            }
+            (Access::Token { full: true, .. }, command) => {
+                info!(
+                    "Refused {} from {peer} on the control socket without the token",
+                    command.name()
+                );
+                Response::Error(ResponseError::ReadOnly)
+            }
+            (Access::Token { token, .. }, Message::Authenticated(given, msg))
+                if tokens_equal(&given, token) =>
+            {
+                if msg.is_read_only() {
+                    handle(&mut state, *msg)?
+                } else {
+                    Response::Error(ResponseError::ReadOnly)
+                }
+            }
+            (Access::Token { .. }, _) => {
+                warn!("Rejected remote message without valid token");
+                Response::Error(ResponseError::Unauthorized)
+            }
+        };
+        if !full {
+            hide_audit(&mut response);
+        }
+        if changes {
+            state.publish_changes(true);
+        }
+        let subscribed = subscribe && matches!(response, Response::Ok);
+        let response = response.encode()?;
+        Ok(if subscribed {
+            Answer::Subscribe(response)
+        } else {
+            Answer::Respond(Some(response))
+        })
+    }
+
+    fn handle_message(state: &mut State, shutdown: &AtomicBool, msg: Message) -> Result<Response> {
+        Ok(match msg {
+            Message::List(query) => {
+                let mut timers: Vec<&TimerState> = state
+                    .timers
+                    .iter()
+                    .filter(|t| {
+                        query
+                            .tag
+                            .as_ref()
+                            .is_none_or(|tag| t.config.tags.contains(tag))
+                    })
+                    .filter(|t| !query.overdue || t.is_overdue())
+                    .filter(|t| {
+                        !query.running
+                            || matches!(t.status(), TimerStatus::Running | TimerStatus::Fired)
+                    })
+                    .collect();
+                match query.sort {
+                    Some(SortKey::Remaining) => timers.sort_by_key(|t| t.remaining()),
+                    Some(SortKey::Elapsed) => timers.sort_by_key(|t| std::cmp::Reverse(t.clock)),
+                    Some(SortKey::Name) => timers.sort_by(|a, b| a.config.name.cmp(&b.config.name)),
+                    None => {}
+                }
+                Response::List(
+                    timers
+                        .into_iter()
+                        .map(|t| (t.config.name.clone(), state.info(t)))
+                        .collect(),
+                )
+            }
+            Message::Get(name) => match state.find_timer(&name) {
+                Ok(i) => Response::Timer(state.info(&state.timers[i])),
+                Err(e) => Response::Error(e),
+            },
+            Message::Describe(name) => match state.find_timer(&name) {
+                Ok(i) => {
+                    let timer = &state.timers[i];
+                    let source = match (timer.config.kind, &state.config.path) {
+                        (TimerType::Countdown, _) => "countdown".to_string(),
+                        (_, Some(path)) => path.display().to_string(),
+                        (_, None) => "default configuration".to_string(),
+                    };
+                    let configured_name = state
+                        .renames
+                        .iter()
+                        .find(|(_, name)| **name == timer.config.name)
+                        .map(|(configured, _)| configured.clone());
+                    Response::Details(
+                        state.info(timer),
+                        TimerDetails {
+                            name: timer.config.name.clone(),
+                            source,
+                            extends: timer.config.extends.clone(),
+                            configured_name,
+                            settings: timer.config.describe(),
+                        },
+                    )
+                }
+                Err(e) => Response::Error(e),
+            },
+            Message::Reset(name) => match state.find_timer(&name) {
+                Ok(i) if !state.timers[i].config.reset_by.manual() => {
+                    Response::Error(ResponseError::NotResettable)
+                }
+                Ok(i) => {
+                    let name = state.timers[i].config.name.clone();
+                    state.user_reset(|t| t.config.name == name);
+                    Response::Ok
+                }
+                Err(e) => Response::Error(e),
+            },
+            Message::Break(name, duration) => match state.find_timer(&name) {
+                Ok(i) if state.timers[i].config.kind != TimerType::Break => {
+                    Response::Error(ResponseError::NoBreaks)
+                }
+                // A completed break resets the timer
+                Ok(i) if !state.timers[i].config.reset_by.manual() => {
+                    Response::Error(ResponseError::NotResettable)
+                }
+                Ok(i) => {
+                    let timer = &mut state.timers[i];
+                    if let Some(duration) = duration.or(timer.config.duration) {
+                        info!(
+                            "Started break of timer {} for {duration:?}",
+                            timer.config.name
+                        );
+                        timer.on_break = Some(duration);
+                        timer.dismiss_nagbar();
+                        log_event(
+                            &mut state.events,
+                            Event::BreakStarted {
+                                timer: &timer.config.name,
+                                secs: duration.as_secs(),
+                            },
+                        );
+                        Response::Ok
+                    } else {
+                        Response::Error(ResponseError::MissingDuration)
+                    }
+                }
+                Err(e) => Response::Error(e),
+            },
+            Message::ResetTagged(tag) => {
+                state.user_reset(|t| t.config.tags.contains(&tag));
+                Response::Ok
+            }
+            Message::Countdown(name, duration) => {
+                if state.timers.iter().any(|t| t.config.name == name) {
+                    Response::Error(ResponseError::AlreadyExists)
+                } else {
+                    info!("Started countdown {name} of {duration:?}");
+                    state
+                        .timers
+                        .push(TimerState::new(TimerConfig::countdown(name, duration))?);
+                    Response::Ok
+                }
+            }
+            Message::Status => Response::Status(DaemonStatus {
+                version: crate::VERSION.to_string(),
+                capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+                activity_source: state.activity_source.map(|s| s.to_string()),
+                activity_age: state
+                    .activity
+                    .as_ref()
+                    .map(|_| state.activity_seen.elapsed()),
+                remote_session: state.remote_session,
+                focused_app: state.focused.as_ref().map(|w| w.class.clone()),
+                notifications_available: state.notification_retry.is_none(),
+                next: state
+                    .timers
+                    .iter()
+                    .min_by_key(|t| t.remaining())
+                    .map(|t| (t.config.name.clone(), t.remaining())),
+                audit: state.audit.iter().cloned().collect(),
+            }),
+            Message::Shutdown => {
+                info!("Shutdown requested");
+                shutdown.store(true, Ordering::Relaxed);
+                Response::Ok
+            }
+            Message::Input => {
+                if let Some(simulation) = &mut state.simulation {
+                    simulation.input_elapsed = Duration::ZERO;
+                    Response::Ok
+                } else {
+                    Response::Error(ResponseError::Unsupported)
+                }
+            }
+            Message::ExportState => Response::State(
+                state
+                    .timers
+                    .iter()
+                    .map(|t| TimerSnapshot {
+                        name: t.config.name.clone(),
+                        elapsed_secs: t.clock.as_secs(),
+                        went_off: t.went_off,
+                    })
+                    .collect(),
+            ),
+            // Rewrites the clocks like a reset, which can be undone
+            Message::ImportState(snapshots) => {
+                let mut imports = Vec::new();
+                for snapshot in snapshots {
+                    match state
+                        .timers
+                        .iter()
+                        .position(|t| t.config.name == snapshot.name)
+                    {
+                        Some(i) if !state.timers[i].config.reset_by.manual() => {
+                            return Ok(Response::Error(ResponseError::NotResettable));
+                        }
+                        Some(i) => imports.push((i, snapshot)),
+                        None => warn!("Skipped import of unknown timer {}", snapshot.name),
+                    }
+                }
+                let mut previous = Vec::new();
+                for (i, snapshot) in imports {
+                    let timer = &mut state.timers[i];
+                    previous.push(ResetUndo::of(timer));
+                    timer.reset();
+                    timer.clock = Duration::from_secs(snapshot.elapsed_secs);
+                    timer.went_off = snapshot.went_off;
+                    if matches!(timer.config.kind, TimerType::Daily | TimerType::App) {
+                        timer.thresholds_passed = timer.passed_thresholds();
+                    }
+                    info!("Imported clock of timer {}", snapshot.name);
+                }
+                if !previous.is_empty() {
+                    state.undo = Some((Instant::now(), previous));
+                }
+                Response::Ok
+            }
+            Message::ResetAll(except) => {
+                state.user_reset(|t| !except.contains(&t.config.name));
+                Response::Ok
+            }
+            // Answered from the last update, so clients don't cause queries of the activity source
+            Message::Inactive => match state.inactive {
+                Some((_, elapsed)) if state.simulation.is_some() => Response::Duration(elapsed),
+                Some((at, elapsed)) => Response::Duration(elapsed + at.elapsed()),
+                None => Response::Error(ResponseError::Unsupported),
+            },
+            // Connections subscribe in handle_connection, batches and datagrams can't
+            Message::Subscribe => Response::Error(ResponseError::Unsupported),
+            Message::Undo => match state.undo.take() {
+                Some((reset_at, previous)) if reset_at.elapsed() <= state.undo_window => {
+                    for undo in previous {
+                        if let Some(timer) =
+                            state.timers.iter_mut().find(|t| t.config.name == undo.name)
+                        {
+                            undo.restore(timer);
+                        }
+                    }
+                    info!("Undid the last reset");
+                    Response::Ok
+                }
+                _ => Response::Error(ResponseError::NothingToUndo),
+            },
+            Message::Batch(messages) => Response::Batch(
+                messages
+                    .into_iter()
+                    .map(|msg| Self::handle_message(state, shutdown, msg))
+                    .collect::<Result<_>>()?,
+            ),
+            // Local clients don't need a token
+            Message::Authenticated(_, msg) => Self::handle_message(state, shutdown, *msg)?,
+            Message::Snooze(name, duration) => match state.find_timer(&name) {
+                Ok(i) if !state.timers[i].config.reset_by.manual() => {
+                    Response::Error(ResponseError::NotResettable)
+                }
+                Ok(i) => {
+                    let timer = &mut state.timers[i];
+                    info!("Snoozed timer {} for {duration:?}", timer.config.name);
+                    timer.clock = timer.config.interval.saturating_sub(duration);
+                    timer.went_off = false;
+                    timer.dismiss_nagbar();
+                    Response::Ok
+                }
+                Err(e) => Response::Error(e),
+            },
+            Message::Rename(name, new_name) => match state.find_timer(&name) {
+                Ok(_) if state.timers.iter().any(|t| t.config.name == new_name) => {
+                    Response::Error(ResponseError::AlreadyExists)
+                }
+                Ok(i) => {
+                    let timer = &mut state.timers[i];
+                    let old_name = std::mem::replace(&mut timer.config.name, new_name.clone());
+                    info!("Renamed timer {old_name} to {new_name}");
+                    if timer.config.kind != TimerType::Countdown {
+                        let configured = state
+                            .renames
+                            .iter()
+                            .find(|(_, name)| **name == old_name)
+                            .map(|(configured, _)| configured.clone())
+                            .unwrap_or(old_name);
+                        if configured == new_name {
+                            state.renames.remove(&configured);
+                        } else {
+                            state.renames.insert(configured, new_name);
+                        }
+                        state.save()?;
+                    }
+                    Response::Ok
+                }
+                Err(e) => Response::Error(e),
+            },
+            Message::DryRun(msg) => {
+                let affected: Result<Vec<&TimerState>, ResponseError> = match *msg {
+                    Message::Reset(name) => match state.find_timer(&name) {
+                        Ok(i) if !state.timers[i].config.reset_by.manual() => {
+                            Err(ResponseError::NotResettable)
+                        }
+                        result => result.map(|i| vec![&state.timers[i]]),
+                    },
+                    Message::ResetTagged(tag) => Ok(state
+                        .timers
+                        .iter()
+                        .filter(|t| t.config.tags.contains(&tag) && t.config.reset_by.manual())
+                        .collect()),
+                    Message::ResetAll(except) => Ok(state
+                        .timers
+                        .iter()
+                        .filter(|t| !except.contains(&t.config.name) && t.config.reset_by.manual())
+                        .collect()),
+                    _ => Err(ResponseError::Unsupported),
+                };
+                match affected {
+                    Ok(timers) => Response::List(
+                        timers
+                            .into_iter()
+                            .map(|t| (t.config.name.clone(), state.info(t)))
+                            .collect(),
+                    ),
+                    Err(e) => Response::Error(e),
+                }
+            }
+            Message::Request(id, msg) => {
+                Response::Reply(id, Box::new(Self::handle_message(state, shutdown, *msg)?))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn edit_distance_counts_single_edits() {
+        assert_eq!(edit_distance("move", "move"), 0);
+        assert_eq!(edit_distance("move", "mvoe"), 2);
+        assert_eq!(edit_distance("move", "moves"), 1);
+        assert_eq!(edit_distance("eyes", "eye"), 1);
+        assert_eq!(edit_distance("", "eyes"), 4);
+        assert_eq!(edit_distance("stretch", "screen"), 4);
+    }
+
+    #[test]
+    fn day_starts_at_reset_time() {
+        let tz = FixedOffset::east_opt(2 * 60 * 60).unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let at = |h, m| tz.with_ymd_and_hms(2024, 3, 10, h, m, 0).unwrap();
+        assert_eq!(day_of(&at(3, 59), 4 * HOUR), date(9));
+        assert_eq!(day_of(&at(4, 0), 4 * HOUR), date(10));
+        assert_eq!(day_of(&at(23, 59), 4 * HOUR), date(10));
+        assert_eq!(day_of(&at(0, 0), Duration::ZERO), date(10));
+    }
+
+    #[test]
+    fn reminder_bounds_around_times() {
+        let at = [9 * HOUR, 13 * HOUR, 17 * HOUR];
+        assert_eq!(reminder_bounds(&at, 8 * HOUR), (Duration::ZERO, 9 * HOUR));
+        assert_eq!(reminder_bounds(&at, 9 * HOUR), (9 * HOUR, 13 * HOUR));
+        assert_eq!(reminder_bounds(&at, 14 * HOUR), (13 * HOUR, 17 * HOUR));
+        assert_eq!(reminder_bounds(&at, 20 * HOUR), (17 * HOUR, 24 * HOUR));
+        // The times don't have to be in order
+        assert_eq!(
+            reminder_bounds(&[17 * HOUR, 9 * HOUR], 10 * HOUR),
+            (9 * HOUR, 17 * HOUR)
+        );
+        assert_eq!(reminder_bounds(&[], 10 * HOUR), (Duration::ZERO, 24 * HOUR));
+    }
+}
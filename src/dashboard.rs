@@ -0,0 +1,202 @@
+use crate::config::DashboardConfig;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tiny_http::{Header, Method, Server};
+use tracing::{debug, info, warn};
+
+const PAGE: &str = include_str!("dashboard.html");
+/// Interval at which the shutdown flag is checked
+const POLL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Snapshot of the daemon shown on the dashboard
+#[derive(Debug, Clone, Serialize)]
+pub struct Overview {
+    /// Seconds since the last input, if known
+    pub idle_secs: Option<u64>,
+    pub timers: Vec<TimerOverview>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimerOverview {
+    pub name: String,
+    pub elapsed_secs: u64,
+    pub interval_secs: u64,
+    pub status: String,
+    pub overdue: bool,
+}
+
+/// Requests from the dashboard and the JSON API that need the daemon state
+pub enum Request {
+    Overview,
+    Timer(String),
+    /// Reset of a timer by an authorized client with the given address
+    Reset(String, Option<SocketAddr>),
+}
+
+pub enum Reply {
+    Overview(Overview),
+    Timer(TimerOverview),
+    Ok,
+    NotFound,
+    /// The request isn't allowed for the timer
+    Forbidden,
+}
+
+pub struct Dashboard {
+    server: Server,
+    token: Option<String>,
+    /// Origins of the page when only clients on this computer can connect, empty otherwise
+    local_origins: Vec<String>,
+}
+
+impl Dashboard {
+    pub fn bind(config: &DashboardConfig) -> Result<Self> {
+        let addr = config.listen;
+        let server = Server::http(addr).map_err(|e| anyhow!("Failed to bind dashboard: {e}"))?;
+        info!("Serving dashboard on http://{addr}");
+        let local_origins = if addr.ip().is_loopback() {
+            vec![
+                format!("http://{addr}"),
+                format!("http://localhost:{}", addr.port()),
+            ]
+        } else {
+            if config.token.is_none() {
+                warn!("The dashboard on {addr} has no token, so it doesn't allow resets");
+            }
+            Vec::new()
+        };
+        Ok(Self {
+            server,
+            token: config.token.clone(),
+            local_origins,
+        })
+    }
+
+    /// Whether the request may change timers, which needs the token if one is configured
+    ///
+    /// Without a token, only the page of the dashboard and clients other than browsers on this
+    /// computer may, not other pages open in the browser. Those send their own origin, also
+    /// when their host name resolves to the loopback address.
+    fn authorized(&self, request: &tiny_http::Request) -> bool {
+        let header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.as_str())
+        };
+        match &self.token {
+            Some(token) => header("Authorization")
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .is_some_and(|given| crate::daemon::tokens_equal(given, token)),
+            None if self.local_origins.is_empty() => false,
+            None => {
+                header("Origin").is_none_or(|origin| self.local_origins.iter().any(|o| o == origin))
+            }
+        }
+    }
+
+    pub fn serve_until(
+        &self,
+        shutdown: Arc<AtomicBool>,
+        mut handler: impl FnMut(Request) -> Reply,
+    ) -> Result<()> {
+        while !shutdown.load(Ordering::Relaxed) {
+            let Some(request) = self.server.recv_timeout(POLL_TIMEOUT)? else {
+                continue;
+            };
+            debug!("Dashboard request {} {}", request.method(), request.url());
+            let url = request.url().to_string();
+            let segments: Vec<String> = url
+                .split('?')
+                .next()
+                .unwrap_or_default()
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(decode_path)
+                .collect();
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+            let result = match (request.method(), segments.as_slice()) {
+                (Method::Get, []) => request.respond(with_type(PAGE, "text/html")),
+                (Method::Get, ["overview"]) => respond(request, handler(Request::Overview)),
+                // The API lists only the timers, the dashboard also needs the idle time
+                (Method::Get, ["timers"]) => match handler(Request::Overview) {
+                    Reply::Overview(overview) => request.respond(json(&overview.timers)),
+                    reply => respond(request, reply),
+                },
+                (Method::Get, ["timers", name]) => {
+                    respond(request, handler(Request::Timer(name.to_string())))
+                }
+                (Method::Post, ["timers", _, "reset"]) if !self.authorized(&request) => {
+                    warn!("Refused reset over HTTP without a valid token");
+                    request.respond(status(401))
+                }
+                (Method::Post, ["timers", name, "reset"]) => {
+                    let addr = request.remote_addr().copied();
+                    respond(request, handler(Request::Reset(name.to_string(), addr)))
+                }
+                _ => request.respond(status(404)),
+            };
+            if let Err(e) = result {
+                warn!("Failed to respond to dashboard request: {e}");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn with_type(body: &str, content_type: &str) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", content_type).expect("valid header");
+    tiny_http::Response::from_string(body).with_header(header)
+}
+
+fn respond(request: tiny_http::Request, reply: Reply) -> std::io::Result<()> {
+    match reply {
+        Reply::Overview(overview) => request.respond(json(&overview)),
+        Reply::Timer(timer) => request.respond(json(&timer)),
+        Reply::Ok => request.respond(status(204)),
+        Reply::NotFound => request.respond(status(404)),
+        Reply::Forbidden => request.respond(status(403)),
+    }
+}
+
+fn json(value: &impl Serialize) -> tiny_http::Response<std::io::Cursor<Vec<u8>>> {
+    let body = serde_json::to_string(value).expect("serializable value");
+    with_type(&body, "application/json")
+}
+
+fn status(code: u16) -> tiny_http::Response<std::io::Empty> {
+    tiny_http::Response::empty(code)
+}
+
+/// Decodes the percent-encoding of a path segment
+fn decode_path(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
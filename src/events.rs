@@ -0,0 +1,73 @@
+use crate::{config::Rotation, logging::RotatingFile};
+use anyhow::Result;
+use serde::Serialize;
+use std::{io::Write, path::Path, time::SystemTime};
+
+/// Event of the daemon, written to the event log
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Fired {
+        timer: &'a str,
+    },
+    Reset {
+        timer: &'a str,
+        reason: ResetReason,
+    },
+    /// All timers were reset because there was no input
+    InactivityReset,
+    BreakStarted {
+        timer: &'a str,
+        secs: u64,
+    },
+    /// No more input, the timers are paused
+    Idle,
+    /// Input after being idle
+    Active,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetReason {
+    User,
+    /// The break started by the user is over
+    Break,
+    /// The time of the daily reset passed
+    Schedule,
+    /// A pause shortly before the timer would go off counted as the break
+    Credit,
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    /// Unix time in milliseconds
+    time: u128,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// Append-only log with an event as JSON object per line, e.g.
+/// `{"time":1700000000000,"event":"fired","timer":"move"}`
+pub struct EventLog {
+    file: RotatingFile,
+}
+
+impl EventLog {
+    /// Opens the log, which is only rotated when the rotation has limits
+    pub fn open(path: &Path, rotation: Rotation) -> Result<Self> {
+        Ok(Self {
+            file: RotatingFile::open(path, rotation)?,
+        })
+    }
+
+    /// Appends the event as one line, which is written at once for readers following the file
+    pub fn write(&mut self, event: &Event) -> Result<()> {
+        let time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_millis();
+        let mut line = serde_json::to_string(&Line { time, event })?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
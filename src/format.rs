@@ -0,0 +1,262 @@
+use crate::{
+    config::{ClockStyle, DurationStyle, FormatConfig},
+    msg::{TimerInfo, TimerStatus},
+};
+use chrono::{DateTime, TimeZone};
+use std::{fmt::Display, io::IsTerminal, time::Duration};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+
+/// Fraction of the interval from which a timer is shown as almost due
+const WARN_PERCENTAGE: f64 = 0.75;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Colors text by how far the timer is in its interval, overdue timers are shown in bold
+pub fn paint(text: &str, percentage: f64, overdue: bool, color: bool) -> String {
+    if !color {
+        return text.to_string();
+    }
+    let color = if percentage >= 1.0 {
+        RED
+    } else if percentage >= WARN_PERCENTAGE {
+        YELLOW
+    } else {
+        GREEN
+    };
+    let bold = if overdue { BOLD } else { "" };
+    format!("{bold}{color}{text}{RESET}")
+}
+
+/// Replaces the default block characters of the bar by plain ASCII ones
+pub fn ascii_bar(s: &str) -> &str {
+    match s {
+        "█" => "#",
+        "░" => "-",
+        "▕" => "[",
+        "▏" => "]",
+        s => s,
+    }
+}
+
+/// Renders a bar that is filled by the given fraction
+pub fn bar(percentage: f64, size: usize, fill: &str, empty: &str) -> String {
+    let fill_count = (size as f64 * percentage.clamp(0.0, 1.0)).round() as usize;
+    fill.repeat(fill_count) + &empty.repeat(size - fill_count)
+}
+
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let m = secs / 60;
+    let s = secs % 60;
+    format!("{m:02}:{s:02}")
+}
+
+/// Formats a duration in hours and minutes, e.g. `1h05`
+pub fn format_hours(d: Duration) -> String {
+    let mins = d.as_secs() / 60;
+    format!("{}h{:02}", mins / 60, mins % 60)
+}
+
+/// Formats a duration in its largest unit, e.g. `42m`, `1h05` or `30s`
+pub fn format_short(d: Duration) -> String {
+    match d.as_secs() {
+        s if s >= 3600 => format_hours(d),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+/// Formats a duration in hours and minutes with a colon, e.g. `1:05`
+pub fn format_hmm(d: Duration) -> String {
+    let mins = d.as_secs() / 60;
+    format!("{}:{:02}", mins / 60, mins % 60)
+}
+
+/// Formats a duration in its two largest units, e.g. `1h 5m`, `5m 30s` or `42s`
+pub fn format_humanized(d: Duration) -> String {
+    let secs = d.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, 0) => format!("{m}m"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, 0, _) => format!("{h}h"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+/// Renders durations and times of the day in the configured style
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Formatter {
+    duration: DurationStyle,
+    clock: ClockStyle,
+}
+
+impl Formatter {
+    pub fn new(config: &FormatConfig) -> Self {
+        Self {
+            duration: config.duration,
+            clock: config.clock,
+        }
+    }
+
+    /// Formats a clock or an interval
+    pub fn duration(&self, d: Duration) -> String {
+        match self.duration {
+            DurationStyle::MinutesSeconds => format_duration(d),
+            DurationStyle::HoursMinutes => format_hmm(d),
+            DurationStyle::Humanized => format_humanized(d),
+        }
+    }
+
+    /// Formats a total that may span hours, like the active time of a day
+    pub fn total(&self, d: Duration) -> String {
+        match self.duration {
+            DurationStyle::MinutesSeconds => format_hours(d),
+            DurationStyle::HoursMinutes => format_hmm(d),
+            DurationStyle::Humanized => format_humanized(d),
+        }
+    }
+
+    /// Formats the time of the day, e.g. `17:30` or `5:30 PM`
+    pub fn time<Tz: TimeZone>(&self, t: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: Display,
+    {
+        match self.clock {
+            ClockStyle::TwentyFourHour => t.format("%H:%M").to_string(),
+            ClockStyle::TwelveHour => t.format("%-I:%M %p").to_string(),
+        }
+    }
+
+    /// Formats the time of the day with seconds, e.g. `17:30:05` or `5:30:05 PM`
+    pub fn time_seconds<Tz: TimeZone>(&self, t: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: Display,
+    {
+        match self.clock {
+            ClockStyle::TwentyFourHour => t.format("%H:%M:%S").to_string(),
+            ClockStyle::TwelveHour => t.format("%-I:%M:%S %p").to_string(),
+        }
+    }
+}
+
+/// Formats a duration as it is read out, e.g. `2 hours and 5 minutes`
+pub fn format_spoken(d: Duration) -> String {
+    let mins = d.as_secs() / 60;
+    match (mins / 60, mins % 60) {
+        (0, 0) => crate::tr!("spoken-less-than-minute"),
+        (0, m) => crate::tr!("spoken-minutes", count = m),
+        (h, 0) => crate::tr!("spoken-hours", count = h),
+        (h, m) => crate::tr!(
+            "spoken-hours-minutes",
+            hours = crate::tr!("spoken-hours", count = h),
+            minutes = crate::tr!("spoken-minutes", count = m),
+        ),
+    }
+}
+
+/// Formats a duration in the ISO 8601 format, e.g. `PT1H2M3S`
+pub fn format_iso8601(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let mut out = "PT".to_string();
+    if h > 0 {
+        out += &format!("{h}H");
+    }
+    if m > 0 {
+        out += &format!("{m}M");
+    }
+    if s > 0 || (h == 0 && m == 0) {
+        out += &format!("{s}S");
+    }
+    out
+}
+
+/// Header of the CSV timer output
+pub const CSV_HEADER: &str = "name,elapsed,interval,status,overdue";
+
+/// Formats a timer as a CSV record
+pub fn csv(name: &str, info: &TimerInfo) -> String {
+    let name = if name.contains([',', '"', '\n']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    };
+    format!(
+        "{name},{},{},{},{}",
+        format_iso8601(info.elapsed),
+        format_iso8601(info.interval),
+        status_token(info.status),
+        info.overdue
+    )
+}
+
+fn status_token(status: TimerStatus) -> &'static str {
+    match status {
+        TimerStatus::Running => "running",
+        TimerStatus::PausedIdle => "paused-idle",
+        TimerStatus::PausedBreak => "paused-break",
+        TimerStatus::Fired => "fired",
+        TimerStatus::PausedApp => "paused-app",
+    }
+}
+
+/// Version of the porcelain output format, increased on every incompatible change
+pub const PORCELAIN_VERSION: u32 = 1;
+
+/// Formats a timer as a stable, machine-readable line
+///
+/// The line consists of tab-separated fields:
+/// `v<version> <name> <elapsed secs> <interval secs> <status> <overdue>`,
+/// where status is one of `running`, `paused-idle`, `paused-break`, `paused-app` or `fired`
+/// and overdue is `1` or `0`.
+pub fn porcelain(name: &str, info: &TimerInfo) -> String {
+    format!(
+        "v{PORCELAIN_VERSION}\t{name}\t{}\t{}\t{}\t{}",
+        info.elapsed.as_secs(),
+        info.interval.as_secs(),
+        status_token(info.status),
+        info.overdue as u8
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iso8601_leaves_out_zero_parts() {
+        let secs = Duration::from_secs;
+        assert_eq!(format_iso8601(secs(0)), "PT0S");
+        assert_eq!(format_iso8601(secs(59)), "PT59S");
+        assert_eq!(format_iso8601(secs(60)), "PT1M");
+        assert_eq!(format_iso8601(secs(3600)), "PT1H");
+        assert_eq!(format_iso8601(secs(3723)), "PT1H2M3S");
+        assert_eq!(format_iso8601(secs(3603)), "PT1H3S");
+        assert_eq!(format_iso8601(secs(25 * 3600)), "PT25H");
+    }
+}
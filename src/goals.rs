@@ -0,0 +1,109 @@
+use crate::{
+    config::{Goal, GoalPeriod},
+    format::Formatter,
+    history::Period,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Days, Local};
+use std::{fmt, time::Duration, time::SystemTime};
+
+/// How far a goal is reached in its current period
+pub struct Progress {
+    /// The goal, e.g. `at least 8 breaks per day` in the configured language
+    pub goal: String,
+    /// Current value, e.g. `5` or `7h12`
+    pub value: String,
+    pub met: bool,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.met {
+            crate::tr!("goal-met")
+        } else {
+            crate::tr!("goal-not-met")
+        };
+        write!(f, "{}: {} ({status})", self.goal, self.value)
+    }
+}
+
+/// Evaluates a goal over the periods of its current day, week or month
+///
+/// Breaks are the pauses between periods that are at least `break_length`.
+pub fn evaluate(
+    goal: &Goal,
+    periods: &[Period],
+    break_length: Duration,
+    now: DateTime<Local>,
+    fmt: &Formatter,
+) -> Result<Progress> {
+    let since = period_start(goal.per, now)?;
+    // Selects the word of the period in the translations
+    let per = match goal.per {
+        GoalPeriod::Day => "day",
+        GoalPeriod::Week => "week",
+        GoalPeriod::Month => "month",
+    };
+    Ok(match (goal.min_breaks, goal.max_active, goal.min_active) {
+        (Some(min), None, None) => {
+            let breaks = breaks(periods, since, break_length);
+            Progress {
+                goal: crate::tr!("goal-min-breaks", count = min, per = per),
+                value: breaks.to_string(),
+                met: breaks >= min,
+            }
+        }
+        (None, Some(max), None) => {
+            let active = active(periods, since);
+            Progress {
+                goal: crate::tr!("goal-max-active", time = fmt.total(max), per = per),
+                value: fmt.total(active),
+                met: active <= max,
+            }
+        }
+        (None, None, Some(min)) => {
+            let active = active(periods, since);
+            Progress {
+                goal: crate::tr!("goal-min-active", time = fmt.total(min), per = per),
+                value: fmt.total(active),
+                met: active >= min,
+            }
+        }
+        _ => bail!("A goal needs exactly one of min_breaks, max_active or min_active"),
+    })
+}
+
+/// Start of the current day, week or month
+fn period_start(period: GoalPeriod, now: DateTime<Local>) -> Result<SystemTime> {
+    let today = now.date_naive();
+    let first = match period {
+        GoalPeriod::Day => Some(today),
+        GoalPeriod::Week => {
+            today.checked_sub_days(Days::new(now.weekday().num_days_from_monday().into()))
+        }
+        GoalPeriod::Month => today.with_day(1),
+    };
+    first
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(|t| t.and_local_timezone(Local).earliest())
+        .map(SystemTime::from)
+        .context("Invalid start of the goal period")
+}
+
+/// Active time since the given time
+fn active(periods: &[Period], since: SystemTime) -> Duration {
+    periods
+        .iter()
+        .filter_map(|p| p.end.duration_since(p.start.max(since)).ok())
+        .sum()
+}
+
+/// Number of pauses between the periods since the given time that are at least `length`
+fn breaks(periods: &[Period], since: SystemTime, length: Duration) -> u32 {
+    let mut periods: Vec<&Period> = periods.iter().filter(|p| p.end >= since).collect();
+    periods.sort_by_key(|p| p.start);
+    periods
+        .windows(2)
+        .filter(|w| w[1].start.duration_since(w[0].end).unwrap_or_default() >= length)
+        .count() as u32
+}
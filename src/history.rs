@@ -0,0 +1,143 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, Timelike};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// Uninterrupted period of user activity
+#[derive(Debug, Clone, Copy)]
+pub struct Period {
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+impl Period {
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+}
+
+/// Appends a period to the history as a line of `<start unix secs> <end unix secs>`
+pub fn append(path: &Path, period: &Period) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create history directory {dir:?}"))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history {path:?}"))?;
+    let secs = |t: SystemTime| {
+        t.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+    };
+    writeln!(file, "{} {}", secs(period.start)?, secs(period.end)?)?;
+    Ok(())
+}
+
+/// Removes the periods that ended before the given time, returns the number of removed periods
+pub fn prune(path: &Path, before: SystemTime) -> Result<usize> {
+    let periods = read(path)?;
+    let kept: Vec<&Period> = periods.iter().filter(|p| p.end >= before).collect();
+    let removed = periods.len() - kept.len();
+    if removed == 0 {
+        return Ok(0);
+    }
+    let secs = |t: SystemTime| {
+        t.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+    };
+    let mut content = String::new();
+    for period in kept {
+        content += &format!("{} {}\n", secs(period.start)?, secs(period.end)?);
+    }
+    // Replace the file at once so that a crash doesn't lose the history
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content).with_context(|| format!("Failed to write {tmp:?}"))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace history {path:?}"))?;
+    Ok(removed)
+}
+
+/// Reads all periods of the history, which is empty when the file doesn't exist
+pub fn read(path: &Path) -> Result<Vec<Period>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(path).with_context(|| format!("Failed to open history {path:?}"))?;
+    let mut periods = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let Some((start, end)) = line.split_once(' ') else {
+            bail!("Invalid history line {}: '{line}'", n + 1);
+        };
+        let parse = |secs: &str| -> Result<SystemTime> {
+            let secs = secs
+                .parse::<u64>()
+                .with_context(|| format!("Invalid timestamp on history line {}", n + 1))?;
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+        };
+        periods.push(Period {
+            start: parse(start)?,
+            end: parse(end)?,
+        });
+    }
+    Ok(periods)
+}
+
+/// Periods that ended within the last days
+pub fn recent(periods: &[Period], days: u32) -> Vec<Period> {
+    let since = SystemTime::now() - Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+    periods.iter().filter(|p| p.end >= since).copied().collect()
+}
+
+/// Active time per hour of the day for each of the last days, oldest first
+pub fn heatmap(periods: &[Period], days: u32) -> Vec<(NaiveDate, [Duration; 24])> {
+    let today = Local::now().date_naive();
+    let first = today - chrono::Days::new(days.saturating_sub(1).into());
+    let mut map: BTreeMap<NaiveDate, [Duration; 24]> = first
+        .iter_days()
+        .take_while(|d| *d <= today)
+        .map(|d| (d, [Duration::ZERO; 24]))
+        .collect();
+    for period in periods {
+        let mut cursor = DateTime::<Local>::from(period.start);
+        let end = DateTime::<Local>::from(period.end);
+        // Split the period on hour boundaries
+        while cursor < end {
+            let hour_start = cursor
+                .with_minute(0)
+                .and_then(|t| t.with_second(0))
+                .and_then(|t| t.with_nanosecond(0))
+                .unwrap_or(cursor);
+            let segment_end = (hour_start + chrono::Duration::hours(1)).min(end);
+            if let Some(hours) = map.get_mut(&cursor.date_naive()) {
+                hours[cursor.hour() as usize] +=
+                    (segment_end - cursor).to_std().unwrap_or_default();
+            }
+            cursor = segment_end;
+        }
+    }
+    map.into_iter().collect()
+}
+
+/// Total active time and longest period for each of the last days, oldest first
+pub fn daily_summary(periods: &[Period], days: u32) -> Vec<(NaiveDate, Duration, Duration)> {
+    let mut longest: BTreeMap<NaiveDate, Duration> = BTreeMap::new();
+    for period in periods {
+        let day = DateTime::<Local>::from(period.start).date_naive();
+        let entry = longest.entry(day).or_default();
+        *entry = (*entry).max(period.duration());
+    }
+    heatmap(periods, days)
+        .into_iter()
+        .map(|(day, hours)| {
+            let total = hours.iter().sum();
+            (day, total, longest.get(&day).copied().unwrap_or_default())
+        })
+        .collect()
+}
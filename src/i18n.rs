@@ -0,0 +1,116 @@
+//! Translations of the notifications and the command line output
+//!
+//! Messages are looked up in the bundled Fluent translation of the locale, falling back to
+//! English for the ones it lacks. The messages of the configuration replace both.
+use crate::config::LanguageConfig;
+use anyhow::{anyhow, Context, Result};
+use fluent_bundle::{concurrent::FluentBundle, FluentResource};
+use parking_lot::RwLock;
+use std::sync::LazyLock;
+use tracing::{debug, warn};
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentArgs;
+
+/// Bundled translations by language, English is the fallback for all of them
+const TRANSLATIONS: &[(&str, &str)] = &[
+    ("en", include_str!("i18n/en.ftl")),
+    ("nl", include_str!("i18n/nl.ftl")),
+    ("de", include_str!("i18n/de.ftl")),
+];
+
+/// Messages in use, from the environment until configured
+static BUNDLE: LazyLock<RwLock<FluentBundle<FluentResource>>> = LazyLock::new(|| {
+    RwLock::new(bundle(&LanguageConfig::default()).expect("bundled translations have to be valid"))
+});
+
+/// Translates a message, with the arguments given as `name = value`
+///
+/// ```ignore
+/// tr!("timer-went-off", name = timer.config.name.as_str())
+/// ```
+#[macro_export]
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::translate($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::translate($id, Some(&args))
+    }};
+}
+
+/// Switches to the locale and messages of the configuration
+pub fn init(config: &LanguageConfig) -> Result<()> {
+    *BUNDLE.write() = bundle(config)?;
+    Ok(())
+}
+
+/// Formats the message, or returns its ID when no translation has it
+pub fn translate(id: &str, args: Option<&FluentArgs>) -> String {
+    let bundle = BUNDLE.read();
+    let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) else {
+        warn!("Missing translation of {id}");
+        return id.to_string();
+    };
+    let mut errors = Vec::new();
+    let text = bundle.format_pattern(pattern, args, &mut errors);
+    if !errors.is_empty() {
+        warn!("Failed to format {id}: {errors:?}");
+    }
+    text.into_owned()
+}
+
+/// Locale of the configuration or else of the environment, English when neither is usable
+pub fn locale(config: &LanguageConfig) -> Result<LanguageIdentifier> {
+    if let Some(locale) = &config.locale {
+        return locale
+            .parse()
+            .with_context(|| format!("Invalid locale '{locale}'"));
+    }
+    // Same order of precedence as gettext
+    let env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    let Some(env) = env else {
+        return Ok(english());
+    };
+    // POSIX locales look like `nl_NL.UTF-8@euro`
+    let name = env.split(['.', '@']).next().unwrap_or_default();
+    match name {
+        "C" | "POSIX" => Ok(english()),
+        name => Ok(name.replace('_', "-").parse().unwrap_or_else(|_| {
+            debug!("Ignoring unknown locale {env}");
+            english()
+        })),
+    }
+}
+
+fn english() -> LanguageIdentifier {
+    "en".parse().expect("en is a valid locale")
+}
+
+fn bundle(config: &LanguageConfig) -> Result<FluentBundle<FluentResource>> {
+    let locale = locale(config)?;
+    let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+    // Isolation marks show up as odd characters in terminals and notifications
+    bundle.set_use_isolating(false);
+    let language = locale.language.as_str();
+    for (name, source) in TRANSLATIONS {
+        if *name == "en" || *name == language {
+            let resource = FluentResource::try_new(source.to_string())
+                .map_err(|(_, e)| anyhow!("Invalid {name} translation: {e:?}"))?;
+            bundle.add_resource_overriding(resource);
+        }
+    }
+    for (id, message) in &config.messages {
+        // Continuation lines of a Fluent message have to be indented
+        let source = format!("{id} = {}\n", message.replace('\n', "\n    "));
+        let resource = FluentResource::try_new(source)
+            .map_err(|(_, e)| anyhow!("Invalid message {id}: {e:?}"))?;
+        bundle.add_resource_overriding(resource);
+    }
+    Ok(bundle)
+}
@@ -0,0 +1,125 @@
+## Notifications
+
+timer-went-off = Timer { $name } ist abgelaufen
+timers-went-off = Timer { $names } und { $last } sind abgelaufen
+break-complete = Pause von { $name } beendet
+break-complete-body = Deine Pause ist vorbei.
+welcome-back = Willkommen zurück
+welcome-back-body = Deine Timer wurden zurückgesetzt, während du weg warst.
+goals-met = { $met } von { $total } Zielen erreicht
+reminder-time = Es ist { $time }
+daily-active = Du warst heute { $time } aktiv
+daily-app = Du hast heute { $time } in { $name } verbracht
+take-a-break = Zeit für eine Pause!
+countdown-finished = Countdown abgelaufen
+nag-overdue = { $name } ist seit { $overdue } überfällig, Zeit für eine Pause!
+
+## Speech
+
+spoken-break = Zeit für eine Pause, du hast { $worked } gearbeitet
+spoken-break-over = Deine { $name } Pause ist vorbei
+spoken-countdown = Countdown { $name } ist abgelaufen
+spoken-reminder = Erinnerung: { $name }
+spoken-minutes = { $count ->
+    [one] 1 Minute
+   *[other] { $count } Minuten
+}
+spoken-hours = { $count ->
+    [one] 1 Stunde
+   *[other] { $count } Stunden
+}
+spoken-hours-minutes = { $hours } und { $minutes }
+spoken-less-than-minute = weniger als eine Minute
+
+## Command line output
+
+reset-none = Es würden keine Timer zurückgesetzt
+reset-would = Würde { $name } bei { $time } zurücksetzen ({ $status })
+timer-left = noch { $time } ({ $status })
+timer-overdue = { $time } überfällig ({ $status })
+timer-held-back = Benachrichtigung seit { $deferred } von höchstens { $cap } zurückgehalten
+timer-today = heute: { $count ->
+    [one] einmal
+   *[other] { $count } Mal
+} abgelaufen, { $total } insgesamt
+timer-source = Quelle: { $source }
+timer-renamed = umbenannt von: { $name }
+timer-extends = erweitert: { $name }
+next-now = nächster: { $name } jetzt
+next-in = nächster: { $name } in { $time }
+status-capabilities = Fähigkeiten: { $capabilities }
+status-version-differs = moved { $daemon } unterscheidet sich von movebeam { $client }, starte es neu für den vollständigen Status
+status-activity = Aktivitätsquelle: { $source }
+status-activity-age = Aktivitätsquelle: { $source }, letzte Antwort vor { $age }
+status-session-local = Sitzung: lokal
+status-session-remote = Sitzung: entfernt
+status-focused-app = fokussierte App: { $app }
+status-notifications-unavailable = Benachrichtigungen: nicht verfügbar, neuer Versuch folgt
+status-changes = letzte Änderungen:
+status-change = { $time } { $message } durch { $peer } ({ $process })
+status-exited = beendet
+stats-date = Datum
+stats-active = aktiv
+stats-longest = längste
+history-removed = Aktivitätsverlauf entfernt
+history-pruned = { $count } Zeiträume älter als { $days } Tage entfernt
+goals-none = Keine Ziele konfiguriert
+goal-min-breaks = mindestens { $count } Pausen pro { $per ->
+    [week] Woche
+    [month] Monat
+   *[day] Tag
+}
+goal-max-active = höchstens { $time } aktiv pro { $per ->
+    [week] Woche
+    [month] Monat
+   *[day] Tag
+}
+goal-min-active = mindestens { $time } aktiv pro { $per ->
+    [week] Woche
+    [month] Monat
+   *[day] Tag
+}
+goal-met = erreicht
+goal-not-met = nicht erreicht
+
+## Doctor
+
+doctor-hint = Tipp: { $hint }
+doctor-config = Konfiguration
+doctor-config-ok = { $count ->
+    [one] 1 Timer
+   *[other] { $count } Timer
+}
+doctor-config-hint = korrigiere die Konfigurationsdatei oder entferne sie, um die Standardwerte zu verwenden
+doctor-moved-ok = läuft mit { $count ->
+    [one] 1 Timer
+   *[other] { $count } Timern
+}
+doctor-moved-hint = starte den Timer-Daemon mit `moved`
+doctor-unexpected-response = unerwartete Antwort
+doctor-actived-ok = letzte Eingabe vor { $time }
+doctor-actived-hint = starte den Aktivitäts-Daemon als root, z. B. `sudo actived`
+doctor-socket = Socket-Berechtigungen
+doctor-socket-missing = { $socket } existiert nicht
+doctor-socket-unwritable = { $socket } für diesen Benutzer nicht beschreibbar ({ $mode })
+doctor-socket-hint = starte actived neu, damit es seinen Socket neu erstellt, oder füge dich seiner Gruppe hinzu, wenn es mit `--user` läuft
+doctor-display = Anzeige
+doctor-display-none = keine grafische Sitzung gefunden
+doctor-display-hint = starte in einer Wayland- oder X11-Sitzung, um Benachrichtigungen zu erhalten
+doctor-notifications = Benachrichtigungen
+doctor-notifications-hint = installiere und starte einen Benachrichtigungs-Daemon wie dunst oder mako
+
+## Command line errors
+
+error-not-found = Timer nicht gefunden!
+error-similar = Timer nicht gefunden, meintest du { $names }?
+error-missing-duration = Keine Pausendauer angegeben!
+error-unsupported = Vom Daemon nicht unterstützt!
+error-already-exists = Timer existiert bereits!
+error-read-only-remote = Aus der Ferne sind nur Abfragen erlaubt!
+error-read-only = Dieser Socket erlaubt nur Abfragen, verwende --control für Änderungen!
+error-unauthorized = Ungültiges Token!
+error-nothing-to-undo = Kein Zurücksetzen zum Rückgängigmachen!
+error-not-resettable = Timer kann nicht vom Benutzer zurückgesetzt werden!
+error-no-breaks = Nur Pausentimer können pausieren!
+error-unknown-message = moved versteht die Nachricht nicht, starte es neu, um es zu aktualisieren!
@@ -0,0 +1,125 @@
+## Notifications
+
+timer-went-off = Timer { $name } went off
+timers-went-off = Timers { $names } and { $last } went off
+break-complete = Break of { $name } complete
+break-complete-body = Your break is over.
+welcome-back = Welcome back
+welcome-back-body = Your timers were reset while you were away.
+goals-met = Met { $met } of { $total } goals
+reminder-time = It's { $time }
+daily-active = You've been active for { $time } today
+daily-app = You've spent { $time } in { $name } today
+take-a-break = Time to take a break!
+countdown-finished = Countdown finished
+nag-overdue = { $name } is overdue by { $overdue }, time to take a break!
+
+## Speech
+
+spoken-break = Time to take a break, you've worked for { $worked }
+spoken-break-over = Your { $name } break is over
+spoken-countdown = Countdown { $name } finished
+spoken-reminder = Reminder: { $name }
+spoken-minutes = { $count ->
+    [one] 1 minute
+   *[other] { $count } minutes
+}
+spoken-hours = { $count ->
+    [one] 1 hour
+   *[other] { $count } hours
+}
+spoken-hours-minutes = { $hours } and { $minutes }
+spoken-less-than-minute = less than a minute
+
+## Command line output
+
+reset-none = No timers would be reset
+reset-would = Would reset { $name } at { $time } ({ $status })
+timer-left = { $time } left ({ $status })
+timer-overdue = { $time } overdue ({ $status })
+timer-held-back = notification held back for { $deferred } of at most { $cap }
+timer-today = today: went off { $count ->
+    [one] once
+   *[other] { $count } times
+}, { $total } in total
+timer-source = source: { $source }
+timer-renamed = renamed from: { $name }
+timer-extends = extends: { $name }
+next-now = next: { $name } now
+next-in = next: { $name } in { $time }
+status-capabilities = capabilities: { $capabilities }
+status-version-differs = moved { $daemon } differs from movebeam { $client }, restart it for the full status
+status-activity = activity source: { $source }
+status-activity-age = activity source: { $source }, last answer { $age } ago
+status-session-local = session: local
+status-session-remote = session: remote
+status-focused-app = focused app: { $app }
+status-notifications-unavailable = notifications: unavailable, retrying
+status-changes = recent changes:
+status-change = { $time } { $message } by { $peer } ({ $process })
+status-exited = exited
+stats-date = date
+stats-active = active
+stats-longest = longest
+history-removed = Removed the activity history
+history-pruned = Removed { $count } periods older than { $days } days
+goals-none = No goals configured
+goal-min-breaks = at least { $count } breaks per { $per ->
+    [week] week
+    [month] month
+   *[day] day
+}
+goal-max-active = at most { $time } active per { $per ->
+    [week] week
+    [month] month
+   *[day] day
+}
+goal-min-active = at least { $time } active per { $per ->
+    [week] week
+    [month] month
+   *[day] day
+}
+goal-met = met
+goal-not-met = not met
+
+## Doctor
+
+doctor-hint = hint: { $hint }
+doctor-config = config
+doctor-config-ok = { $count ->
+    [one] 1 timer
+   *[other] { $count } timers
+}
+doctor-config-hint = fix the configuration file or remove it to use the defaults
+doctor-moved-ok = running with { $count ->
+    [one] 1 timer
+   *[other] { $count } timers
+}
+doctor-moved-hint = start the timer daemon with `moved`
+doctor-unexpected-response = unexpected response
+doctor-actived-ok = last input { $time } ago
+doctor-actived-hint = start the activity daemon as root, e.g. `sudo actived`
+doctor-socket = socket permissions
+doctor-socket-missing = { $socket } doesn't exist
+doctor-socket-unwritable = { $socket } not writable by this user ({ $mode })
+doctor-socket-hint = restart actived so that it recreates its socket, or when it runs with `--user`, add yourself to its group
+doctor-display = display
+doctor-display-none = no graphical session found
+doctor-display-hint = run inside a Wayland or X11 session to receive notifications
+doctor-notifications = notifications
+doctor-notifications-hint = install and start a notification daemon such as dunst or mako
+
+## Command line errors
+
+error-not-found = Timer not found!
+error-similar = Timer not found, did you mean { $names }?
+error-missing-duration = No break duration specified!
+error-unsupported = Not supported by the daemon!
+error-already-exists = Timer already exists!
+error-read-only-remote = Only queries are allowed remotely!
+error-read-only = Only queries are allowed on this socket, use --control for changes!
+error-unauthorized = Invalid token!
+error-nothing-to-undo = No reset to undo!
+error-not-resettable = Timer can't be reset by the user!
+error-no-breaks = Only break timers can take a break!
+error-unknown-message = moved doesn't understand the message, restart it to update it!
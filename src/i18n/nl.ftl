@@ -0,0 +1,125 @@
+## Notifications
+
+timer-went-off = Timer { $name } is afgegaan
+timers-went-off = Timers { $names } en { $last } zijn afgegaan
+break-complete = Pauze van { $name } voorbij
+break-complete-body = Je pauze is voorbij.
+welcome-back = Welkom terug
+welcome-back-body = Je timers zijn teruggezet terwijl je weg was.
+goals-met = { $met } van de { $total } doelen gehaald
+reminder-time = Het is { $time }
+daily-active = Je bent vandaag { $time } actief geweest
+daily-app = Je hebt vandaag { $time } in { $name } doorgebracht
+take-a-break = Tijd voor een pauze!
+countdown-finished = Aftelling is klaar
+nag-overdue = { $name } is { $overdue } over tijd, tijd voor een pauze!
+
+## Speech
+
+spoken-break = Tijd voor een pauze, je hebt { $worked } gewerkt
+spoken-break-over = Je { $name } pauze is voorbij
+spoken-countdown = Aftelling { $name } is klaar
+spoken-reminder = Herinnering: { $name }
+spoken-minutes = { $count ->
+    [one] 1 minuut
+   *[other] { $count } minuten
+}
+spoken-hours = { $count ->
+    [one] 1 uur
+   *[other] { $count } uur
+}
+spoken-hours-minutes = { $hours } en { $minutes }
+spoken-less-than-minute = minder dan een minuut
+
+## Command line output
+
+reset-none = Er zouden geen timers worden gereset
+reset-would = Zou { $name } resetten op { $time } ({ $status })
+timer-left = nog { $time } ({ $status })
+timer-overdue = { $time } over tijd ({ $status })
+timer-held-back = melding { $deferred } van maximaal { $cap } uitgesteld
+timer-today = vandaag: { $count ->
+    [one] één keer
+   *[other] { $count } keer
+} afgegaan, { $total } in totaal
+timer-source = bron: { $source }
+timer-renamed = hernoemd van: { $name }
+timer-extends = breidt uit: { $name }
+next-now = volgende: { $name } nu
+next-in = volgende: { $name } over { $time }
+status-capabilities = mogelijkheden: { $capabilities }
+status-version-differs = moved { $daemon } verschilt van movebeam { $client }, herstart het voor de volledige status
+status-activity = activiteitsbron: { $source }
+status-activity-age = activiteitsbron: { $source }, laatste antwoord { $age } geleden
+status-session-local = sessie: lokaal
+status-session-remote = sessie: op afstand
+status-focused-app = actieve app: { $app }
+status-notifications-unavailable = meldingen: niet beschikbaar, wordt opnieuw geprobeerd
+status-changes = recente wijzigingen:
+status-change = { $time } { $message } door { $peer } ({ $process })
+status-exited = beëindigd
+stats-date = datum
+stats-active = actief
+stats-longest = langste
+history-removed = Activiteitsgeschiedenis verwijderd
+history-pruned = { $count } periodes ouder dan { $days } dagen verwijderd
+goals-none = Geen doelen ingesteld
+goal-min-breaks = minstens { $count } pauzes per { $per ->
+    [week] week
+    [month] maand
+   *[day] dag
+}
+goal-max-active = hoogstens { $time } actief per { $per ->
+    [week] week
+    [month] maand
+   *[day] dag
+}
+goal-min-active = minstens { $time } actief per { $per ->
+    [week] week
+    [month] maand
+   *[day] dag
+}
+goal-met = gehaald
+goal-not-met = niet gehaald
+
+## Doctor
+
+doctor-hint = tip: { $hint }
+doctor-config = configuratie
+doctor-config-ok = { $count ->
+    [one] 1 timer
+   *[other] { $count } timers
+}
+doctor-config-hint = herstel het configuratiebestand of verwijder het om de standaardinstellingen te gebruiken
+doctor-moved-ok = actief met { $count ->
+    [one] 1 timer
+   *[other] { $count } timers
+}
+doctor-moved-hint = start de timerdaemon met `moved`
+doctor-unexpected-response = onverwacht antwoord
+doctor-actived-ok = laatste invoer { $time } geleden
+doctor-actived-hint = start de activiteitsdaemon als root, bijvoorbeeld `sudo actived`
+doctor-socket = socketrechten
+doctor-socket-missing = { $socket } bestaat niet
+doctor-socket-unwritable = { $socket } niet schrijfbaar voor deze gebruiker ({ $mode })
+doctor-socket-hint = herstart actived zodat het de socket opnieuw aanmaakt, of voeg jezelf toe aan de groep als het met `--user` draait
+doctor-display = beeldscherm
+doctor-display-none = geen grafische sessie gevonden
+doctor-display-hint = start binnen een Wayland- of X11-sessie om meldingen te ontvangen
+doctor-notifications = meldingen
+doctor-notifications-hint = installeer en start een meldingsdaemon zoals dunst of mako
+
+## Command line errors
+
+error-not-found = Timer niet gevonden!
+error-similar = Timer niet gevonden, bedoelde je { $names }?
+error-missing-duration = Geen pauzeduur opgegeven!
+error-unsupported = Niet ondersteund door de daemon!
+error-already-exists = Timer bestaat al!
+error-read-only-remote = Op afstand zijn alleen opvragingen toegestaan!
+error-read-only = Deze socket staat alleen opvragingen toe, gebruik --control voor wijzigingen!
+error-unauthorized = Ongeldig token!
+error-nothing-to-undo = Geen reset om ongedaan te maken!
+error-not-resettable = Timer kan niet door de gebruiker worden gereset!
+error-no-breaks = Alleen pauzetimers kunnen pauzeren!
+error-unknown-message = moved begrijpt het bericht niet, herstart het om het bij te werken!
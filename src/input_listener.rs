@@ -1,20 +1,70 @@
+use crate::activity::InputEvent;
 use crossbeam_channel::Sender;
-use evdev::{Device, EventType};
+use evdev::{AbsoluteAxisType, Device, EventType, InputEvent as RawEvent, Key};
 use tokio_stream::{StreamExt, StreamMap};
 use tracing::{error, info};
 
-pub enum InputEvent {
-    Keyboard,
-    Mouse,
+/// Fraction of the axis range a gamepad stick has to move to count as input, to ignore stick drift
+const STICK_THRESHOLD: f64 = 0.25;
+
+/// Ranges of the absolute axes of a gamepad or joystick
+struct Gamepad {
+    ranges: Vec<(i32, i32)>,
+}
+
+impl Gamepad {
+    fn detect(device: &Device) -> Option<Self> {
+        let keys = device.supported_keys()?;
+        if !keys.contains(Key::BTN_SOUTH) && !keys.contains(Key::BTN_TRIGGER) {
+            return None;
+        }
+        let ranges = device
+            .get_abs_state()
+            .map(|state| state.iter().map(|a| (a.minimum, a.maximum)).collect())
+            .unwrap_or_default();
+        Some(Self { ranges })
+    }
+
+    fn is_input(&self, event: &RawEvent) -> bool {
+        match event.event_type() {
+            EventType::KEY => true,
+            EventType::ABSOLUTE => {
+                let hats = AbsoluteAxisType::ABS_HAT0X.0..=AbsoluteAxisType::ABS_HAT3Y.0;
+                if hats.contains(&event.code()) {
+                    return true;
+                }
+                let Some((min, max)) = self.ranges.get(event.code() as usize) else {
+                    return false;
+                };
+                let center = (*min as f64 + *max as f64) / 2.0;
+                let half_range = (*max as f64 - *min as f64) / 2.0;
+                (event.value() as f64 - center).abs() > STICK_THRESHOLD * half_range
+            }
+            _ => false,
+        }
+    }
 }
 
-pub fn start_listener(event_tx: Sender<InputEvent>) {
+/// Opens the keyboard, mouse, touchscreen & gamepad devices, which requires root
+pub fn open_devices() -> Vec<Device> {
+    evdev::enumerate()
+        .map(|(_, device)| device)
+        .filter(|d| {
+            let supported = d.supported_events();
+            supported.contains(EventType::KEY)
+                || supported.contains(EventType::RELATIVE)
+                || supported.contains(EventType::ABSOLUTE)
+        })
+        .collect()
+}
+
+pub fn start_listener(devices: Vec<Device>, event_tx: Sender<InputEvent>) {
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
         .unwrap();
     rt.block_on(async {
-        if let Err(e) = run(event_tx).await {
+        if let Err(e) = run(devices, event_tx).await {
             error!("Failed to run event listener: {e}");
         }
     });
@@ -28,30 +78,26 @@ enum RunError {
     Std(#[from] std::io::Error),
 }
 
-async fn run(event_tx: Sender<InputEvent>) -> Result<(), RunError> {
-    let devices: Vec<Device> = evdev::enumerate()
-        .map(|(_, device)| device)
-        .filter(|d| {
-            // Filter on keyboard, mouse & touchscreen devices
-            let supported = d.supported_events();
-            supported.contains(EventType::KEY)
-                || supported.contains(EventType::RELATIVE)
-                || supported.contains(EventType::ABSOLUTE)
-        })
-        .collect();
+async fn run(devices: Vec<Device>, event_tx: Sender<InputEvent>) -> Result<(), RunError> {
     if devices.is_empty() {
         return Err(RunError::NoDevices);
     }
     info!("Listening for events on {} input devices", devices.len());
     let mut streams = StreamMap::new();
+    let mut gamepads = Vec::new();
     for (n, device) in devices.into_iter().enumerate() {
+        gamepads.push(Gamepad::detect(&device));
         streams.insert(n, device.into_event_stream()?);
     }
-    while let Some((_, Ok(event))) = streams.next().await {
-        let event = match event.event_type() {
-            EventType::KEY => Some(InputEvent::Keyboard),
-            EventType::RELATIVE | EventType::ABSOLUTE => Some(InputEvent::Mouse),
-            _ => None,
+    while let Some((n, Ok(event))) = streams.next().await {
+        let event = if let Some(gamepad) = &gamepads[n] {
+            gamepad.is_input(&event).then_some(InputEvent::Gamepad)
+        } else {
+            match event.event_type() {
+                EventType::KEY => Some(InputEvent::Keyboard),
+                EventType::RELATIVE | EventType::ABSOLUTE => Some(InputEvent::Mouse),
+                _ => None,
+            }
         };
         if let Some(event) = event {
             if let Err(e) = event_tx.try_send(event) {
@@ -0,0 +1,67 @@
+use anyhow::{Context, Result};
+use std::{fs, path::PathBuf};
+
+/// LED of the sysfs LED class, e.g. a keyboard backlight or the caps lock LED
+///
+/// Writing the brightness usually needs a udev rule granting access to the user.
+pub struct Led {
+    path: PathBuf,
+    original: u32,
+    max: u32,
+    pulsing: bool,
+    lit: bool,
+}
+
+impl Led {
+    /// Opens the LED by its name in `/sys/class/leds`
+    pub fn open(name: &str) -> Result<Self> {
+        let path = PathBuf::from("/sys/class/leds").join(name);
+        let original = read_value(&path.join("brightness"))?;
+        let max = read_value(&path.join("max_brightness"))?;
+        Ok(Self {
+            path,
+            original,
+            max,
+            pulsing: false,
+            lit: false,
+        })
+    }
+
+    /// Switches between the full and the original brightness
+    pub fn toggle(&mut self) -> Result<()> {
+        self.pulsing = true;
+        self.lit = !self.lit;
+        let brightness = if self.lit {
+            self.max
+        } else if self.original == self.max {
+            0
+        } else {
+            self.original
+        };
+        self.write(brightness)
+    }
+
+    /// Restores the original brightness if the LED was pulsing
+    pub fn restore(&mut self) -> Result<()> {
+        if !self.pulsing {
+            return Ok(());
+        }
+        self.pulsing = false;
+        self.lit = false;
+        self.write(self.original)
+    }
+
+    fn write(&self, brightness: u32) -> Result<()> {
+        let path = self.path.join("brightness");
+        fs::write(&path, brightness.to_string())
+            .with_context(|| format!("Failed to write {path:?}"))
+    }
+}
+
+fn read_value(path: &PathBuf) -> Result<u32> {
+    fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {path:?}"))?
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid value in {path:?}"))
+}
@@ -1,23 +1,95 @@
+pub mod activity;
+#[cfg(feature = "border")]
+pub mod border;
+pub mod calendar;
 pub mod cli;
 pub mod config;
+pub mod daemon;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+pub mod events;
+pub mod format;
+pub mod goals;
+pub mod history;
+pub mod i18n;
+#[cfg(feature = "evdev")]
 pub mod input_listener;
+pub mod led;
+pub mod logging;
 pub mod msg;
+pub mod nag;
+pub mod persistence;
+pub mod portal;
+pub mod recording;
+pub mod sandbox;
 pub mod socket;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod window;
 
-use anyhow::{Context, Result};
-use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use std::{
+    fs, io,
+    os::unix::fs::{DirBuilderExt, MetadataExt},
+    path::PathBuf,
+};
 use tracing::{debug, error};
 
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const DAEMON_NAME: &str = "moved";
 pub const ACTIVITY_DAEMON_NAME: &str = "actived";
 
-pub fn daemon_socket() -> PathBuf {
-    dirs::runtime_dir()
-        .expect("No runtime directory found!")
-        .join(APP_NAME)
-        .join(DAEMON_NAME)
-        .with_extension("sock")
+/// Directory of the sockets of the user
+///
+/// This is `$MOVEBEAM_RUNTIME_DIR` if set, otherwise a directory in `$XDG_RUNTIME_DIR` or in
+/// `/tmp` for sessions without one, like cron jobs and ssh logins.
+pub fn runtime_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("MOVEBEAM_RUNTIME_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = dirs::runtime_dir() {
+        return Ok(dir.join(APP_NAME));
+    }
+    // SAFETY: getuid always succeeds
+    let uid = unsafe { libc::getuid() };
+    let dir = PathBuf::from("/tmp").join(format!("{APP_NAME}-{uid}"));
+    // Anyone can create the directory in /tmp, so it's only used if it belongs to the user
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+            return Err(e).with_context(|| format!("Failed to create {dir:?}"));
+        }
+        _ => {}
+    }
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid {
+        bail!("No runtime directory found and {dir:?} belongs to another user");
+    }
+    Ok(dir)
+}
+
+pub fn daemon_socket() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join(DAEMON_NAME).with_extension("sock"))
+}
+
+/// PID file of the running daemon, which also serves as the lock of the single instance
+pub fn daemon_pid_file() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join(DAEMON_NAME).with_extension("pid"))
+}
+
+/// Socket of the daemon that accepts changes when the daemon socket is read-only
+pub fn daemon_control_socket() -> Result<PathBuf> {
+    Ok(daemon_socket()?.with_extension("control.sock"))
+}
+
+/// Token that clients of the control socket send, only readable by the owner
+pub fn daemon_control_token() -> Result<PathBuf> {
+    Ok(daemon_socket()?.with_extension("control.token"))
+}
+
+/// Datagram socket of the daemon for read-only queries without framing
+pub fn daemon_datagram_socket() -> Result<PathBuf> {
+    Ok(daemon_socket()?.with_extension("dgram.sock"))
 }
 
 pub fn activity_daemon_socket() -> PathBuf {
@@ -33,17 +105,72 @@ pub fn config_path() -> Result<PathBuf> {
         .context("Couldn't find the config directory")
 }
 
+pub fn state_path() -> Result<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|d| d.join(APP_NAME).join("state").with_extension("toml"))
+        .context("Couldn't find the state directory")
+}
+
+/// Log file of the daemon when it runs in the background without a configured one
+pub fn log_path() -> Result<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|d| d.join(APP_NAME).join(DAEMON_NAME).with_extension("log"))
+        .context("Couldn't find the state directory")
+}
+
+/// File with the recorded periods of activity
+pub fn history_path() -> Result<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|d| d.join(APP_NAME).join("history"))
+        .context("Couldn't find the state directory")
+}
+
+/// Reads the text out loud through speech-dispatcher
+pub fn speak(text: &str) {
+    debug!("Speak: {text}");
+    match std::process::Command::new("spd-say")
+        .args(["--application-name", APP_NAME, text])
+        .status()
+    {
+        Ok(status) if !status.success() => error!("Failed to speak, spd-say exited with {status}"),
+        Ok(_) => {}
+        Err(e) => error!("Failed to run spd-say: {e}"),
+    }
+}
+
 /// Sends a desktop notification
 pub fn send_notification(title: String, description: String) {
+    if let Err(e) = try_send_notification(&title, &description) {
+        error!("Failed to send notification: {e:#}");
+    }
+}
+
+/// Sends a desktop notification of low urgency, for information that needs no action
+pub fn try_send_info_notification(title: &str, description: &str) -> Result<()> {
+    show_notification(title, description, notify_rust::Urgency::Low)
+}
+
+/// Sends a desktop notification, fails when no notification daemon is running
+pub fn try_send_notification(title: &str, description: &str) -> Result<()> {
+    show_notification(title, description, notify_rust::Urgency::Normal)
+}
+
+fn show_notification(title: &str, description: &str, urgency: notify_rust::Urgency) -> Result<()> {
     use notify_rust::*;
 
     debug!("Notification: {title} - {description}");
-    if let Err(e) = Notification::new()
-        .summary(&title)
-        .body(&description)
+    if portal::sandboxed() {
+        return portal::notify(title, description, urgency);
+    }
+    Notification::new()
+        .summary(title)
+        .body(description)
         .appname(APP_NAME)
+        .urgency(urgency)
         .show()
-    {
-        error!("Failed to send notification: {e}");
-    }
+        .context("Failed to show notification")?;
+    Ok(())
 }
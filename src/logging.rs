@@ -0,0 +1,168 @@
+use crate::config::{Logging, Rotation};
+use anyhow::{Context, Result};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
+};
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{filter::EnvFilter, fmt, prelude::*};
+
+/// Number of received reopen signals, files reopen when it changed since they were opened
+static REOPEN: AtomicUsize = AtomicUsize::new(0);
+
+/// Initializes the tracing subscriber with the outputs from the logging configuration
+///
+/// The returned guard flushes the log file when dropped and must be kept alive.
+pub fn init(config: &Logging) -> Result<Option<WorkerGuard>> {
+    // Log to journald when running as a systemd service, unless configured otherwise
+    let journald = config
+        .journald
+        .unwrap_or_else(|| std::env::var_os("JOURNAL_STREAM").is_some());
+    let journald_layer = if journald {
+        Some(tracing_journald::layer().with_context(|| "Failed to connect to journald")?)
+    } else {
+        None
+    };
+    // Stderr is already captured by the journal
+    let stderr_layer = if journald_layer.is_none() {
+        Some(fmt::layer())
+    } else {
+        None
+    };
+
+    let (file_layer, guard) = if let Some(path) = &config.file {
+        let (writer, guard) = match config.rotation {
+            Some(rotation) => tracing_appender::non_blocking(RotatingFile::open(path, rotation)?),
+            None => {
+                let dir = path.parent().context("Invalid log file path")?;
+                let name = path.file_name().context("Invalid log file path")?;
+                tracing_appender::non_blocking(rolling::daily(dir, name))
+            }
+        };
+        (
+            Some(fmt::layer().with_ansi(false).with_writer(writer)),
+            Some(guard),
+        )
+    } else {
+        (None, None)
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(journald_layer)
+        .with(file_layer)
+        .with(EnvFilter::builder().from_env().unwrap())
+        .init();
+    Ok(guard)
+}
+
+/// Reopens the rotating files on `SIGUSR1`, e.g. after logrotate moved them
+pub fn reopen_on_signal() -> Result<()> {
+    // SAFETY: the handler only increments an atomic, which is async-signal-safe
+    unsafe {
+        signal_hook::low_level::register(signal_hook::consts::SIGUSR1, || {
+            REOPEN.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+    .context("Failed to handle SIGUSR1")?;
+    Ok(())
+}
+
+/// Appending file that is rotated once it exceeds the limits and reopened on a signal
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// Creation of the file, or when it was opened if unknown
+    since: SystemTime,
+    rotation: Rotation,
+    reopens: usize,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, rotation: Rotation) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {dir:?}"))?;
+        }
+        let (file, size, since) =
+            Self::open_file(path).with_context(|| format!("Failed to open {path:?}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            since,
+            rotation,
+            reopens: REOPEN.load(Ordering::Relaxed),
+        })
+    }
+
+    fn open_file(path: &Path) -> io::Result<(File, u64, SystemTime)> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+        let since = metadata.created().unwrap_or_else(|_| SystemTime::now());
+        Ok((file, metadata.len(), since))
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        (self.file, self.size, self.since) = Self::open_file(&self.path)?;
+        Ok(())
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn needs_rotation(&self) -> bool {
+        let too_large = self.rotation.max_size.is_some_and(|max| self.size >= max);
+        let too_old = self.rotation.max_age.is_some_and(|days| {
+            self.since.elapsed().unwrap_or_default()
+                >= Duration::from_secs(u64::from(days) * 24 * 60 * 60)
+        });
+        self.size > 0 && (too_large || too_old)
+    }
+
+    /// Moves the file to `<file>.1` after shifting the older ones, the oldest is removed
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.rotation.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.rotation.keep).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.reopen()?;
+        // A new file might inherit the creation time of the old one on some filesystems
+        self.since = SystemTime::now();
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let reopens = REOPEN.load(Ordering::Relaxed);
+        if reopens != self.reopens {
+            self.reopens = reopens;
+            self.reopen()?;
+        }
+        if self.needs_rotation() {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
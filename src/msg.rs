@@ -1,33 +1,301 @@
-use crate::cli::CliCommand;
-use anyhow::{Context, Result};
+use crate::cli::{CliCommand, DaemonCommand, StateCommand};
+use anyhow::{bail, Context, Result};
 use bincode::{Decode, Encode};
-use std::{fmt::Debug, time::Duration};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, fs, time::Duration};
 
 #[derive(Debug, Clone, Decode, Encode)]
 pub enum Message {
-    List,
+    List(ListQuery),
     Get(String),
     Reset(String),
-    ResetAll,
+    /// Reset all timers with the given tag
+    ResetTagged(String),
+    /// Reset all timers except the given ones
+    ResetAll(Vec<String>),
+    Break(String, Option<Duration>),
+    /// Start a temporary timer which is removed once it went off
+    Countdown(String, Duration),
+    /// Inject user input, only supported when simulating
+    Input,
+    /// Version and capabilities of the daemon
+    Status,
+    /// Save the state and stop the daemon
+    Shutdown,
+    /// Clocks of all timers
+    ExportState,
+    /// Restore the clocks of the given timers
+    ImportState(Vec<TimerSnapshot>),
+    /// Multiple messages in one round trip, answered with a batch of responses in the same order
+    Batch(Vec<Message>),
+    /// Message with an ID that is answered with a reply carrying the same ID, for clients
+    /// that pipeline requests on one connection
+    Request(u64, Box<Message>),
+    /// Message with the token of the remote listener
+    Authenticated(String, Box<Message>),
+    /// Let the timer go off again after the given time
+    Snooze(String, Duration),
+    /// Rename a timer, keeping its clock
+    Rename(String, String),
+    /// Information of a timer with its effective configuration
+    Describe(String),
+    /// Restore the timers of the last reset within the undo window
+    Undo,
+    /// Answer with the timers a reset would affect, without changing them
+    DryRun(Box<Message>),
+    /// Time since the last input as of the last update, without querying the activity source
+    Inactive,
+    /// Keep the connection open and receive the timers that went off, were reset, paused or
+    /// resumed as they change
+    Subscribe,
 }
 
-impl From<CliCommand> for Message {
-    fn from(val: CliCommand) -> Self {
-        match val {
-            CliCommand::List => Message::List,
-            CliCommand::Get { name } | CliCommand::Bar { name, .. } => Message::Get(name),
-            CliCommand::Reset { name } => Message::Reset(name),
-            CliCommand::ResetAll => Message::ResetAll,
+/// Names of the messages supported by this version
+pub const CAPABILITIES: &[&str] = &[
+    "list",
+    "get",
+    "reset",
+    "reset-tagged",
+    "reset-all",
+    "break",
+    "countdown",
+    "input",
+    "status",
+    "shutdown",
+    "export-state",
+    "import-state",
+    "batch",
+    "request",
+    "authenticated",
+    "snooze",
+    "rename",
+    "describe",
+    "undo",
+    "dry-run",
+    "inactive",
+    "subscribe",
+];
+
+impl Message {
+    /// Name of the message as listed in the capabilities
+    pub fn name(&self) -> &'static str {
+        match self {
+            Message::List(_) => "list",
+            Message::Get(_) => "get",
+            Message::Reset(_) => "reset",
+            Message::ResetTagged(_) => "reset-tagged",
+            Message::ResetAll(_) => "reset-all",
+            Message::Break(..) => "break",
+            Message::Countdown(..) => "countdown",
+            Message::Input => "input",
+            Message::Status => "status",
+            Message::Shutdown => "shutdown",
+            Message::ExportState => "export-state",
+            Message::ImportState(_) => "import-state",
+            Message::Batch(_) => "batch",
+            Message::Request(..) => "request",
+            Message::Authenticated(..) => "authenticated",
+            Message::Snooze(..) => "snooze",
+            Message::Rename(..) => "rename",
+            Message::Describe(_) => "describe",
+            Message::Undo => "undo",
+            Message::DryRun(_) => "dry-run",
+            Message::Inactive => "inactive",
+            Message::Subscribe => "subscribe",
+        }
+    }
+
+    /// Wraps the message in a dry run if requested
+    fn dry_run(self, dry_run: bool) -> Message {
+        if dry_run {
+            Message::DryRun(Box::new(self))
+        } else {
+            self
+        }
+    }
+
+    /// Whether the message only queries the daemon without changing anything
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Message::List(_)
+            | Message::Get(_)
+            | Message::Describe(_)
+            | Message::Status
+            | Message::ExportState
+            | Message::DryRun(_)
+            | Message::Inactive
+            | Message::Subscribe => true,
+            Message::Batch(messages) => messages.iter().all(Message::is_read_only),
+            Message::Request(_, msg) | Message::Authenticated(_, msg) => msg.is_read_only(),
+            _ => false,
         }
     }
+
+    /// Whether the message subscribes the connection to the changes of the timers
+    pub fn subscribes(&self) -> bool {
+        match self {
+            Message::Subscribe => true,
+            Message::Authenticated(_, msg) => msg.subscribes(),
+            _ => false,
+        }
+    }
+}
+
+/// Filters and ordering applied to the timer list
+#[derive(Debug, Clone, Default, Decode, Encode)]
+pub struct ListQuery {
+    pub tag: Option<String>,
+    pub sort: Option<SortKey>,
+    /// Only timers that went over their interval
+    pub overdue: bool,
+    /// Only timers whose clock is currently advancing
+    pub running: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode, clap::ValueEnum)]
+pub enum SortKey {
+    /// Least remaining time first
+    Remaining,
+    /// Most elapsed time first
+    Elapsed,
+    /// Alphabetically by name
+    Name,
+}
+
+impl TryFrom<CliCommand> for Message {
+    type Error = anyhow::Error;
+
+    fn try_from(val: CliCommand) -> Result<Self> {
+        Ok(match val {
+            CliCommand::List {
+                tag,
+                sort,
+                overdue,
+                running,
+                ..
+            } => Message::List(ListQuery {
+                tag,
+                sort,
+                overdue,
+                running,
+            }),
+            CliCommand::Get {
+                name,
+                verbose: true,
+            } => Message::Describe(name),
+            CliCommand::Get { name, .. }
+            | CliCommand::Bar { name, .. }
+            | CliCommand::Wait { name, .. } => Message::Get(name),
+            CliCommand::Reset {
+                tag: Some(tag),
+                dry_run,
+                ..
+            } => Message::ResetTagged(tag).dry_run(dry_run),
+            CliCommand::Reset { name, dry_run, .. } => {
+                Message::Reset(name.unwrap_or_default()).dry_run(dry_run)
+            }
+            CliCommand::ResetAll { except, dry_run } => Message::ResetAll(except).dry_run(dry_run),
+            CliCommand::Undo => Message::Undo,
+            CliCommand::Break { name, duration } => Message::Break(name, duration),
+            CliCommand::Countdown { name, duration } => Message::Countdown(name, duration),
+            CliCommand::Snooze { name, duration } => Message::Snooze(name, duration),
+            CliCommand::Rename { name, new_name } => Message::Rename(name, new_name),
+            CliCommand::Input => Message::Input,
+            CliCommand::Status => Message::Status,
+            CliCommand::Daemon {
+                cmd: DaemonCommand::Stop,
+            } => Message::Shutdown,
+            CliCommand::State {
+                cmd: StateCommand::Export,
+            } => Message::ExportState,
+            CliCommand::State {
+                cmd: StateCommand::Import { file },
+            } => {
+                let json = fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read state file {file:?}"))?;
+                Message::ImportState(
+                    serde_json::from_str(&json).with_context(|| "Failed to parse state file")?,
+                )
+            }
+            CliCommand::Doctor
+            | CliCommand::Service { .. }
+            | CliCommand::Goals
+            | CliCommand::Idle
+            | CliCommand::IdleBar { .. }
+            | CliCommand::Stats { .. } => {
+                bail!("Command doesn't have a daemon message")
+            }
+        })
+    }
+}
+
+/// Effective configuration of a timer and where it came from
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct TimerDetails {
+    pub name: String,
+    /// Configuration file, or how the timer was created otherwise
+    pub source: String,
+    /// Template the timer extends
+    pub extends: Option<String>,
+    /// Name in the configuration if the timer was renamed
+    pub configured_name: Option<String>,
+    /// Settings as name and value
+    pub settings: Vec<(String, String)>,
 }
 
 #[derive(Debug, Clone, Decode, Encode)]
 pub struct TimerInfo {
     pub elapsed: Duration,
     pub interval: Duration,
+    /// Went over the interval including the grace period
+    pub overdue: bool,
+    pub status: TimerStatus,
+    /// Time the notification has been held back while the user is in flow, and the time after
+    /// which it is sent regardless
+    pub deferred: Option<(Duration, Duration)>,
+    /// Whether a blinking bar is currently shown filled, the same for all clients
+    pub blink: bool,
+    /// Number of times the timer went off today
+    pub fired_today: u32,
+    /// Total time the clock advanced today, regardless of resets
+    pub elapsed_today: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+pub enum TimerStatus {
+    Running,
+    /// Paused because there is no user input
+    PausedIdle,
+    /// Paused during a break started by the user
+    PausedBreak,
+    /// Went off and is still counting
+    Fired,
+    /// Paused while an app of `pause_in` is focused, or for an app timer while its app isn't
+    PausedApp,
+}
+
+impl std::fmt::Display for TimerStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimerStatus::Running => write!(f, "running"),
+            TimerStatus::PausedIdle => write!(f, "paused (idle)"),
+            TimerStatus::PausedBreak => write!(f, "paused (break)"),
+            TimerStatus::Fired => write!(f, "fired"),
+            TimerStatus::PausedApp => write!(f, "paused (app)"),
+        }
+    }
+}
+
+/// Runtime state of a timer that can be carried to another daemon
+#[derive(Debug, Clone, Decode, Encode, Serialize, Deserialize)]
+pub struct TimerSnapshot {
+    pub name: String,
+    pub elapsed_secs: u64,
+    pub went_off: bool,
 }
 
+/// Responses of the daemon, new variants are added at the end so that other versions can still
+/// decode the status
 #[derive(Debug, Clone, Decode, Encode)]
 pub enum Response {
     Ok,
@@ -35,16 +303,148 @@ pub enum Response {
     Timer(TimerInfo),
     List(Vec<(String, TimerInfo)>),
     Error(ResponseError),
+    Status(DaemonStatus),
+    State(Vec<TimerSnapshot>),
+    Batch(Vec<Response>),
+    /// Response to a request with an ID
+    Reply(u64, Box<Response>),
+    Details(TimerInfo, TimerDetails),
+    /// Timers that changed, pushed to subscribed connections
+    Changed(Vec<(String, TimerInfo)>),
+}
+
+/// New fields are added at the end, the version and capabilities stay first so that any version
+/// can decode them with [`StatusHeader`]
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct DaemonStatus {
+    pub version: String,
+    pub capabilities: Vec<String>,
+    /// Activity source in use
+    pub activity_source: Option<String>,
+    /// Time since the activity source last answered, a large one means it stopped working
+    pub activity_age: Option<Duration>,
+    /// Whether the session is used through a remote desktop
+    pub remote_session: bool,
+    /// Class of the focused application, if window tracking is available
+    pub focused_app: Option<String>,
+    /// Whether notifications can be sent, they are retried while there is no notification daemon
+    pub notifications_available: bool,
+    /// Timer that is due first and its remaining time, zero if it is already due
+    pub next: Option<(String, Duration)>,
+    /// Recent changes by local clients, oldest first
+    pub audit: Vec<AuditEntry>,
+}
+
+/// Leading fields of the status, which decode regardless of the fields other versions add
+#[derive(Debug, Clone, Decode)]
+pub struct StatusHeader {
+    pub version: String,
+    pub capabilities: Vec<String>,
+}
+
+impl StatusHeader {
+    /// Decodes the version and capabilities of a status response of any version
+    pub fn decode_response(bytes: &[u8]) -> Result<Self> {
+        let (prefix, _) = bincode::decode_from_slice(bytes, bincode::config::standard())
+            .context("Failed to decode")?;
+        match prefix {
+            StatusPrefix::Status(header) => Ok(header),
+            _ => bail!("Not a status response"),
+        }
+    }
+}
+
+/// Variants of [`Response`] up to the status, the ones before are never decoded
+#[allow(dead_code)]
+#[derive(Decode)]
+enum StatusPrefix {
+    Ok,
+    Duration,
+    Timer,
+    List,
+    Error,
+    Status(StatusHeader),
+}
+
+/// Message that changed the daemon and the client that sent it
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct AuditEntry {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    /// Message as sent by the client, e.g. `Reset("move")`
+    pub message: String,
+    pub uid: Option<u32>,
+    pub pid: Option<u32>,
+    /// Name of the process, if it was still running
+    pub process: Option<String>,
 }
 
 #[derive(Debug, Clone, Decode, Encode)]
 pub enum ResponseError {
     NotFound,
+    MissingDuration,
+    Unsupported,
+    AlreadyExists,
+    /// Only read-only messages are allowed
+    ReadOnly,
+    /// The token is missing or wrong
+    Unauthorized,
+    /// No timer has the name, but these have a similar one or start with it
+    Similar(Vec<String>),
+    /// There was no reset within the undo window
+    NothingToUndo,
+    /// The timer can't be reset by the user
+    NotResettable,
+    /// Breaks are only taken from break timers, the others don't pause
+    NoBreaks,
+    /// The message couldn't be decoded, e.g. because the client is of a newer version
+    UnknownMessage,
+}
+
+/// Activity information reported by the activity daemon
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct ActivityInfo {
+    /// Time since the last input, measured on the monotonic clock so that it isn't affected by
+    /// changes of the system time
+    pub input_elapsed: Duration,
+    /// Keyboard events per minute, measured over the last minute, zero when not exposed
+    pub intensity: f64,
+}
+
+/// Detail of the information the activity daemon exposes, from least to most detailed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Decode, Encode, clap::ValueEnum)]
+pub enum Privacy {
+    /// Only the time of the last input
+    Timestamps,
+    /// The number of keyboard events per minute as well
+    Counts,
+    /// Event counts per kind of input device
+    Full,
+}
+
+/// Query to the activity daemon, which is refused with an empty response if the detail isn't allowed
+///
+/// The query for counts is encoded as `[1]`, which is what older versions sent.
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct ActivityQuery {
+    pub detail: Privacy,
+}
+
+/// Response of the activity daemon to a query for full detail
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct ActivityMetrics {
+    pub info: ActivityInfo,
+    /// Mouse events per minute
+    pub mouse: f64,
+    /// Gamepad events per minute
+    pub gamepad: f64,
 }
 
 pub trait Encoding<T> {
     fn decode(bytes: &[u8]) -> Result<T>;
     fn encode(&self) -> Result<Vec<u8>>;
+    /// Appends the encoding to the buffer, which reuses its allocation
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<()>;
 }
 
 impl<T> Encoding<T> for T
@@ -61,4 +461,10 @@ where
         bincode::encode_to_vec(self, bincode::config::standard())
             .with_context(|| "Failed to encode")
     }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) -> Result<()> {
+        bincode::encode_into_std_write(self, buf, bincode::config::standard())
+            .with_context(|| "Failed to encode")?;
+        Ok(())
+    }
 }
@@ -0,0 +1,31 @@
+use anyhow::{Context, Result};
+use std::{
+    env,
+    process::{Child, Command},
+};
+
+/// Snooze time of the nagbar button
+const SNOOZE: &str = "05:00";
+
+/// Shows a swaynag or i3-nagbar with buttons to take a break or snooze the timer
+pub fn show(name: &str, message: &str) -> Result<Child> {
+    let name = shell_quote(name);
+    let take_break = format!("movebeam break {name}");
+    let snooze = format!("movebeam snooze {name} {SNOOZE}");
+    // The dismissing button flag differs between the two
+    let (program, button) = if env::var_os("SWAYSOCK").is_some() {
+        ("swaynag", "-Z")
+    } else {
+        ("i3-nagbar", "-B")
+    };
+    Command::new(program)
+        .args(["-t", "warning", "-m", message])
+        .args([button, "Take break", &take_break])
+        .args([button, "Snooze 5m", &snooze])
+        .spawn()
+        .with_context(|| format!("Failed to run {program}"))
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+use tracing::info;
+
+/// Clock of a daily timer for a specific day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyClock {
+    /// Date in the `YYYY-MM-DD` format
+    pub day: String,
+    pub secs: u64,
+}
+
+/// Counters of a timer for a specific day, independent of its resets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayCounters {
+    /// Date in the `YYYY-MM-DD` format
+    pub day: String,
+    /// Number of times the timer went off
+    pub fired: u32,
+    /// Total time the clock advanced
+    pub secs: u64,
+}
+
+/// Daemon state that is kept across restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersistentState {
+    #[serde(default)]
+    pub daily: BTreeMap<String, DailyClock>,
+    /// Names given at runtime to the configured timers
+    #[serde(default)]
+    pub renames: BTreeMap<String, String>,
+    /// Counters of today by timer name
+    #[serde(default)]
+    pub today: BTreeMap<String, DayCounters>,
+}
+
+impl PersistentState {
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let state_str =
+                fs::read_to_string(path).with_context(|| "Failed to read state file")?;
+            toml::from_str(&state_str).with_context(|| "Failed to parse state file")
+        } else {
+            info!("No state file found, starting fresh");
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create state directory {dir:?}"))?;
+        }
+        let state_str = toml::to_string(self).with_context(|| "Failed to serialize state")?;
+        fs::write(path, state_str).with_context(|| "Failed to write state file")
+    }
+}
+
+/// Clock of a timer shared with other machines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedClock {
+    pub elapsed_secs: u64,
+    /// Unix time of the last reset, the most recent reset wins
+    pub reset: u64,
+}
+
+/// Timer state shared between machines through a synchronized file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SharedState {
+    /// Unix time in milliseconds of the last input on any machine
+    #[serde(default)]
+    pub last_input: u64,
+    #[serde(default)]
+    pub timers: BTreeMap<String, SharedClock>,
+}
+
+impl SharedState {
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let state_str = fs::read_to_string(path).with_context(|| "Failed to read sync file")?;
+            toml::from_str(&state_str).with_context(|| "Failed to parse sync file")
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let state_str = toml::to_string(self).with_context(|| "Failed to serialize sync state")?;
+        // Replace the file at once so that the other machines never read half of it, the
+        // temporary file is per process as they may share the directory
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp, state_str).with_context(|| format!("Failed to write {tmp:?}"))?;
+        fs::rename(&tmp, path).with_context(|| "Failed to replace sync file")
+    }
+}
@@ -0,0 +1,35 @@
+//! Notifications through the XDG desktop portal, the only way out of a Flatpak or Snap sandbox
+use anyhow::{Context, Result};
+use notify_rust::Urgency;
+use std::{collections::HashMap, path::Path};
+use zbus::{blocking::Connection, zvariant::Value};
+
+/// Whether the process runs inside a Flatpak or Snap sandbox
+pub fn sandboxed() -> bool {
+    Path::new("/.flatpak-info").exists() || std::env::var_os("SNAP").is_some()
+}
+
+/// Sends a notification through the portal, which replaces an earlier one with the same title
+pub fn notify(title: &str, body: &str, urgency: Urgency) -> Result<()> {
+    let priority = match urgency {
+        Urgency::Low => "low",
+        Urgency::Normal => "normal",
+        Urgency::Critical => "urgent",
+    };
+    let notification = HashMap::from([
+        ("title", Value::from(title)),
+        ("body", Value::from(body)),
+        ("priority", Value::from(priority)),
+    ]);
+    let connection = Connection::session().context("Failed to connect to the session bus")?;
+    connection
+        .call_method(
+            Some("org.freedesktop.portal.Desktop"),
+            "/org/freedesktop/portal/desktop",
+            Some("org.freedesktop.portal.Notification"),
+            "AddNotification",
+            &(title, notification),
+        )
+        .context("Failed to send notification through the portal")?;
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use crate::activity::InputEvent;
+use anyhow::{bail, Context, Result};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// Records input events as lines of `<unix millis> <keyboard|mouse|gamepad>`
+pub struct TraceWriter {
+    writer: BufWriter<File>,
+}
+
+impl TraceWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create trace file {path:?}"))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, time: SystemTime, event: &InputEvent) -> Result<()> {
+        let millis = time.duration_since(SystemTime::UNIX_EPOCH)?.as_millis();
+        let kind = match event {
+            InputEvent::Keyboard => "keyboard",
+            InputEvent::Mouse => "mouse",
+            InputEvent::Gamepad => "gamepad",
+        };
+        writeln!(self.writer, "{millis} {kind}")?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a recorded trace as offsets from the first event
+pub fn read_trace(path: &Path) -> Result<Vec<(Duration, InputEvent)>> {
+    let file = File::open(path).with_context(|| format!("Failed to open trace file {path:?}"))?;
+    let mut events = Vec::new();
+    let mut start = None;
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let Some((millis, kind)) = line.split_once(' ') else {
+            bail!("Invalid trace line {}: '{line}'", n + 1);
+        };
+        let millis = millis
+            .parse::<u64>()
+            .with_context(|| format!("Invalid timestamp on trace line {}", n + 1))?;
+        let event = match kind {
+            "keyboard" => InputEvent::Keyboard,
+            "mouse" => InputEvent::Mouse,
+            "gamepad" => InputEvent::Gamepad,
+            _ => bail!("Unknown event '{kind}' on trace line {}", n + 1),
+        };
+        let start = *start.get_or_insert(millis);
+        events.push((Duration::from_millis(millis.saturating_sub(start)), event));
+    }
+    Ok(events)
+}
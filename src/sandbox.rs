@@ -0,0 +1,117 @@
+use anyhow::{bail, Context, Result};
+use landlock::{
+    path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+    ABI,
+};
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, TargetArch};
+use std::{
+    collections::BTreeMap,
+    ffi::CString,
+    fs, io,
+    os::unix::fs::{chown, PermissionsExt},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// Paths that stay readable, to enumerate the input devices
+const READABLE_PATHS: &[&str] = &["/dev/input", "/sys", "/proc"];
+
+/// System calls that are never needed after initialization
+const DENIED_SYSCALLS: &[i64] = &[
+    libc::SYS_execve,
+    libc::SYS_execveat,
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_kexec_load,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+];
+
+/// Restricts the filesystem access and system calls of the process
+///
+/// Files that are already open stay usable, so this is applied after the socket is bound.
+pub fn restrict() -> Result<()> {
+    restrict_filesystem()?;
+    restrict_syscalls()
+}
+
+fn restrict_filesystem() -> Result<()> {
+    let abi = ABI::V2;
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))?
+        .create()?
+        .add_rules(path_beneath_rules(READABLE_PATHS, AccessFs::from_read(abi)))?
+        .restrict_self()
+        .context("Failed to apply Landlock ruleset")?;
+    match status.ruleset {
+        RulesetStatus::FullyEnforced => info!("Restricted filesystem access with Landlock"),
+        RulesetStatus::PartiallyEnforced => {
+            warn!("Landlock is only partially supported by the kernel")
+        }
+        RulesetStatus::NotEnforced => warn!("Landlock isn't supported by the kernel"),
+    }
+    Ok(())
+}
+
+fn restrict_syscalls() -> Result<()> {
+    let rules = DENIED_SYSCALLS
+        .iter()
+        .map(|s| (*s, Vec::new()))
+        .collect::<BTreeMap<_, _>>();
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        TargetArch::try_from(std::env::consts::ARCH)?,
+    )?;
+    let program: BpfProgram = filter.try_into()?;
+    seccompiler::apply_filter_all_threads(&program).context("Failed to apply seccomp filter")?;
+    info!("Restricted system calls with seccomp");
+    Ok(())
+}
+
+/// Switches to an unprivileged user, which owns the socket and may share it with its group
+///
+/// Has to be called before any threads are started.
+pub fn drop_privileges(user: &str, socket: &Path) -> Result<()> {
+    let name = CString::new(user)?;
+    // SAFETY: the name is a valid C string and the entry is copied before any other call
+    let (uid, gid) = unsafe {
+        let entry = libc::getpwnam(name.as_ptr());
+        if entry.is_null() {
+            bail!("User {user} doesn't exist");
+        }
+        ((*entry).pw_uid, (*entry).pw_gid)
+    };
+    if let Some(run_dir) = socket.parent() {
+        chown(run_dir, Some(uid), Some(gid))
+            .with_context(|| format!("Failed to change owner of {run_dir:?}"))?;
+        fs::set_permissions(run_dir, fs::Permissions::from_mode(0o755))?;
+    }
+    chown(socket, Some(uid), Some(gid))
+        .with_context(|| format!("Failed to change owner of {socket:?}"))?;
+    // Only the user and members of its group can connect
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o660))?;
+    // SAFETY: plain system calls without pointers besides the empty group list
+    unsafe {
+        if libc::setgroups(0, std::ptr::null()) != 0
+            || libc::setgid(gid) != 0
+            || libc::setuid(uid) != 0
+        {
+            return Err(io::Error::last_os_error()).context("Failed to drop privileges");
+        }
+    }
+    info!("Dropped privileges to user {user}");
+    Ok(())
+}
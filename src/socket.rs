@@ -1,26 +1,369 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use mio::{event::Source, net::UnixListener as MioListener, Events, Interest, Poll, Token, Waker};
+use parking_lot::Mutex;
 use std::{
+    cell::Cell,
+    collections::HashMap,
     fs,
-    io::{BufRead, BufReader, Write},
-    net::Shutdown,
-    os::unix::{
-        net::{UnixListener, UnixStream},
-        prelude::PermissionsExt,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{IpAddr, Shutdown, SocketAddr, TcpStream},
+    os::{
+        fd::AsRawFd,
+        unix::{
+            net::{UnixDatagram, UnixListener, UnixStream},
+            prelude::PermissionsExt,
+        },
     },
     path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
-use tracing::{info, trace, warn};
+use tracing::{debug, info, trace, warn};
 
 const EOT: u8 = 4;
+const LISTENER: Token = Token(0);
+const WAKER: Token = Token(1);
+
+/// Maximum number of concurrent connections, further connections are closed right away
+const MAX_CONNECTIONS: usize = 64;
+/// Requests per second each user can make on average
+const RATE_LIMIT: f64 = 50.0;
+/// Requests each user can make at once
+const RATE_BURST: f64 = 100.0;
+/// Time after which throttled requests are retried
+const THROTTLE_RETRY: Duration = Duration::from_millis(20);
+/// Interval at which the datagram server checks the shutdown flag
+const DATAGRAM_POLL: Duration = Duration::from_millis(500);
+/// Largest datagram that is received
+const MAX_DATAGRAM: usize = 64 * 1024;
+/// Most input buffered for a connection, a peer sending more without the end of a frame is
+/// disconnected
+const MAX_BUFFERED: usize = 1024 * 1024;
+/// Number of buckets from which the ones that are full again are dropped
+const RATE_PRUNE: usize = 1024;
+
+/// Appends a message to the buffer as base64 terminated by an EOT
+pub fn encode_frame(msg: &[u8], buf: &mut Vec<u8>) {
+    let start = buf.len();
+    let len = base64::encoded_len(msg.len(), false).expect("message size overflows");
+    buf.resize(start + len, 0);
+    STANDARD_NO_PAD
+        .encode_slice(msg, &mut buf[start..])
+        .expect("buffer of the encoded length");
+    buf.push(EOT);
+}
+
+/// Decodes the base64 of a frame without its EOT into the buffer, replacing its contents
+pub fn decode_frame(frame: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+    buf.clear();
+    STANDARD_NO_PAD.decode_vec(frame, buf)?;
+    Ok(())
+}
+
+/// Client that requests count against in the rate limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RateKey {
+    User(u32),
+    Address(IpAddr),
+    /// Clients whose user or address is unknown share one bucket
+    Unknown,
+}
+
+/// Token bucket of the requests per client
+struct RateLimiter {
+    buckets: HashMap<RateKey, (f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Takes a token from the bucket of the client, returns `false` if it's empty
+    fn allow(&mut self, key: RateKey) -> bool {
+        if self.buckets.len() >= RATE_PRUNE {
+            // Buckets that had the time to fill up are the same as new ones
+            let refill = Duration::from_secs_f64(RATE_BURST / RATE_LIMIT);
+            self.buckets.retain(|_, (_, last)| last.elapsed() < refill);
+        }
+        let (tokens, last) = self
+            .buckets
+            .entry(key)
+            .or_insert((RATE_BURST, Instant::now()));
+        *tokens = (*tokens + last.elapsed().as_secs_f64() * RATE_LIMIT).min(RATE_BURST);
+        *last = Instant::now();
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Answer of a handler to a message
+pub enum Answer {
+    /// Response to the message, sent as an empty frame when there is none
+    Respond(Option<Vec<u8>>),
+    /// Response after which the connection also receives the published messages
+    Subscribe(Vec<u8>),
+}
+
+/// Sends messages to the connections of a server that subscribed
+#[derive(Clone)]
+pub struct Publisher {
+    queue: Arc<Mutex<Vec<Vec<u8>>>>,
+    waker: Arc<Waker>,
+}
+
+impl Publisher {
+    /// Queues the message and wakes the server to send it
+    pub fn publish(&self, msg: Vec<u8>) -> Result<()> {
+        self.queue.lock().push(msg);
+        self.waker.wake()?;
+        Ok(())
+    }
+}
+
+/// Credentials of a local client, unknown for clients on the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Peer {
+    pub uid: Option<u32>,
+    pub pid: Option<u32>,
+}
+
+impl std::fmt::Display for Peer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.uid, self.pid) {
+            (Some(uid), Some(pid)) => write!(f, "uid {uid}, pid {pid}"),
+            (Some(uid), None) => write!(f, "uid {uid}"),
+            _ => write!(f, "unknown peer"),
+        }
+    }
+}
+
+/// Returns the credentials of the process on the other end of the socket
+fn peer_credentials(stream: &impl AsRawFd) -> Peer {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: the credentials and length point to valid memory of the right size
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Peer::default();
+    }
+    Peer {
+        uid: Some(cred.uid),
+        pid: u32::try_from(cred.pid).ok().filter(|pid| *pid != 0),
+    }
+}
+
+/// Listening side of a transport, from which the server accepts clients
+///
+/// Implementations are polled by the server, so they have to be non-blocking.
+trait Listener: Source + Send {
+    /// Accepts a waiting client, fails with `WouldBlock` when there is none
+    fn accept_stream(&self) -> io::Result<Box<dyn Stream>>;
+}
+
+impl Listener for MioListener {
+    fn accept_stream(&self) -> io::Result<Box<dyn Stream>> {
+        Ok(Box::new(self.accept()?.0))
+    }
+}
+
+impl Listener for mio::net::TcpListener {
+    fn accept_stream(&self) -> io::Result<Box<dyn Stream>> {
+        Ok(Box::new(self.accept()?.0))
+    }
+}
+
+/// Stream of an accepted client, the server side of a [`Transport`]
+trait Stream: Read + Write + Source + Send {
+    /// Credentials of the peer, only known for local clients
+    fn peer(&self) -> Peer;
+
+    /// Bucket of the rate limit, per user for local clients and per address on the network
+    fn rate_key(&self, peer: Peer) -> RateKey;
+}
+
+impl Stream for mio::net::UnixStream {
+    fn peer(&self) -> Peer {
+        peer_credentials(self)
+    }
+
+    fn rate_key(&self, peer: Peer) -> RateKey {
+        peer.uid.map_or(RateKey::Unknown, RateKey::User)
+    }
+}
+
+impl Stream for mio::net::TcpStream {
+    fn peer(&self) -> Peer {
+        Peer::default()
+    }
+
+    fn rate_key(&self, _: Peer) -> RateKey {
+        self.peer_addr()
+            .map_or(RateKey::Unknown, |addr| RateKey::Address(addr.ip()))
+    }
+}
+
+/// Answers the complete frames at the start of the input and removes them from it, as long as
+/// `allow` permits, a missing answer is sent as an empty frame
+fn respond_frames<F>(
+    input: &mut Vec<u8>,
+    msg_buf: &mut Vec<u8>,
+    output: &mut Vec<u8>,
+    f: &F,
+    mut allow: impl FnMut() -> bool,
+) -> Result<()>
+where
+    F: Fn(&[u8]) -> Option<Vec<u8>>,
+{
+    while let Some(end) = input.iter().position(|b| *b == EOT) {
+        if !allow() {
+            break;
+        }
+        let decoded = decode_frame(&input[..end], msg_buf);
+        input.drain(..=end);
+        decoded?;
+        trace!("Received message: {msg_buf:?}");
+        if let Some(resp) = f(msg_buf) {
+            trace!("Responding with: {resp:?}");
+            encode_frame(&resp, output);
+        } else {
+            output.push(EOT);
+        }
+    }
+    Ok(())
+}
+
+struct Connection {
+    stream: Box<dyn Stream>,
+    peer: Peer,
+    rate_key: RateKey,
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    /// Decoded message, kept to reuse its allocation
+    msg_buf: Vec<u8>,
+    /// Whether the published messages are sent to the peer
+    subscribed: bool,
+}
+
+impl Connection {
+    /// Reads all available data and responds to the complete messages
+    ///
+    /// Returns `true` when the connection is closed by the peer.
+    fn read<F>(&mut self, f: &F, limiter: &mut RateLimiter) -> Result<bool>
+    where
+        F: Fn(&[u8], Peer) -> Answer,
+    {
+        let mut closed = false;
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    closed = true;
+                    break;
+                }
+                Ok(n) => {
+                    self.read_buf.extend_from_slice(&buf[..n]);
+                    if self.read_buf.len() > MAX_BUFFERED {
+                        bail!("Peer sent more than {MAX_BUFFERED} bytes without being answered");
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.respond(f, limiter)?;
+        Ok(closed)
+    }
+
+    /// Responds to the complete messages as far as the rate limit allows
+    fn respond<F>(&mut self, f: &F, limiter: &mut RateLimiter) -> Result<()>
+    where
+        F: Fn(&[u8], Peer) -> Answer,
+    {
+        let (rate_key, peer) = (self.rate_key, self.peer);
+        let subscribe = Cell::new(false);
+        respond_frames(
+            &mut self.read_buf,
+            &mut self.msg_buf,
+            &mut self.write_buf,
+            &|msg: &[u8]| match f(msg, peer) {
+                Answer::Respond(resp) => resp,
+                Answer::Subscribe(resp) => {
+                    subscribe.set(true);
+                    Some(resp)
+                }
+            },
+            || limiter.allow(rate_key),
+        )?;
+        self.subscribed |= subscribe.get();
+        Ok(())
+    }
+
+    /// Queues the published messages for a subscribed peer, fails when it doesn't keep up
+    fn push(&mut self, published: &[Vec<u8>]) -> Result<()> {
+        for msg in published {
+            encode_frame(msg, &mut self.write_buf);
+        }
+        if self.write_buf.len() > MAX_BUFFERED {
+            bail!("Subscriber left more than {MAX_BUFFERED} bytes unread");
+        }
+        self.write()
+    }
+
+    /// Whether complete messages are waiting because of the rate limit
+    fn throttled(&self) -> bool {
+        self.read_buf.contains(&EOT)
+    }
+
+    fn interest(&self) -> Interest {
+        if self.write_buf.is_empty() {
+            Interest::READABLE
+        } else {
+            Interest::READABLE | Interest::WRITABLE
+        }
+    }
+
+    /// Writes as much of the pending responses as possible
+    fn write(&mut self) -> Result<()> {
+        while !self.write_buf.is_empty() {
+            match self.stream.write(&self.write_buf) {
+                Ok(n) => {
+                    self.write_buf.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+}
 
 pub struct SocketServer {
-    listener: UnixListener,
-    path: PathBuf,
+    listener: Box<dyn Listener>,
+    poll: Poll,
+    waker: Arc<Waker>,
+    /// Messages for the subscribed connections, sent when the server is woken
+    published: Arc<Mutex<Vec<Vec<u8>>>>,
+    /// Path of a Unix socket, which is removed when the server is dropped
+    path: Option<PathBuf>,
 }
 
 impl SocketServer {
@@ -39,84 +382,400 @@ impl SocketServer {
             // Set Unix permissions so that all users can write to the socket
             fs::set_permissions(&path, fs::Permissions::from_mode(0o722)).unwrap();
         }
+        listener.set_nonblocking(true)?;
         info!("Created at socket at '{}'", path.display());
-        Ok(Self { listener, path })
+        Self::listen(Box::new(MioListener::from_std(listener)), Some(path))
+    }
+
+    /// Listens for clients on the network
+    pub fn bind_tcp(addr: SocketAddr) -> Result<Self> {
+        let listener = mio::net::TcpListener::bind(addr)
+            .with_context(|| format!("Failed to listen on {addr}"))?;
+        info!("Listening on {addr}");
+        Self::listen(Box::new(listener), None)
+    }
+
+    fn listen(mut listener: Box<dyn Listener>, path: Option<PathBuf>) -> Result<Self> {
+        let poll = Poll::new()?;
+        poll.registry()
+            .register(&mut listener, LISTENER, Interest::READABLE)?;
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+        Ok(Self {
+            listener,
+            poll,
+            waker,
+            published: Arc::default(),
+            path,
+        })
+    }
+
+    /// Returns a waker which interrupts the server to check the shutdown flag
+    pub fn waker(&self) -> Arc<Waker> {
+        self.waker.clone()
+    }
+
+    /// Returns a publisher of messages to the connections that subscribed
+    pub fn publisher(&self) -> Publisher {
+        Publisher {
+            queue: self.published.clone(),
+            waker: self.waker.clone(),
+        }
     }
 
-    pub fn handle<F>(&mut self, f: F) -> Result<()>
+    pub fn serve<F>(&mut self, f: F) -> Result<()>
     where
         F: Fn(&[u8]) -> Option<Vec<u8>>,
     {
-        if let Ok((mut stream, _)) = self.listener.accept() {
-            let reader = std::io::BufReader::new(stream.try_clone()?);
-            for msg in reader.split(EOT) {
-                let msg = msg?;
-                let decoded = STANDARD_NO_PAD.decode(&msg)?;
-                trace!("Received message: {decoded:?}");
-                if let Some(resp) = f(&decoded) {
-                    trace!("Responding with: {resp:?}");
-                    let encoded = STANDARD_NO_PAD.encode(&resp);
-                    stream.write_all(&[encoded.as_bytes(), &[EOT]].concat())?;
-                } else {
-                    stream.write_all(&[EOT])?;
+        self.serve_until(Arc::new(AtomicBool::new(false)), |msg, _| {
+            Answer::Respond(f(msg))
+        })
+    }
+
+    /// Serves clients concurrently until the shutdown flag is set and the server is woken
+    ///
+    /// The handler also gets the credentials of the client that sent the message.
+    pub fn serve_until<F>(&mut self, shutdown: Arc<AtomicBool>, f: F) -> Result<()>
+    where
+        F: Fn(&[u8], Peer) -> Answer,
+    {
+        let mut events = Events::with_capacity(128);
+        let mut connections: HashMap<Token, Connection> = HashMap::new();
+        let mut next_token = WAKER.0 + 1;
+        let mut limiter = RateLimiter {
+            buckets: HashMap::new(),
+        };
+        while !shutdown.load(Ordering::Relaxed) {
+            let throttled = connections.values().any(Connection::throttled);
+            let timeout = throttled.then_some(THROTTLE_RETRY);
+            if let Err(e) = self.poll.poll(&mut events, timeout) {
+                if e.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e.into());
+            }
+            if throttled {
+                for (token, conn) in connections.iter_mut() {
+                    if conn.throttled() {
+                        conn.respond(&f, &mut limiter)?;
+                        if let Err(e) = conn.write() {
+                            debug!("Failed to write response: {e}");
+                        }
+                        let interest = conn.interest();
+                        self.poll
+                            .registry()
+                            .reregister(&mut conn.stream, *token, interest)?;
+                    }
+                }
+            }
+            for event in events.iter() {
+                match event.token() {
+                    LISTENER => loop {
+                        match self.listener.accept_stream() {
+                            Ok(_) if connections.len() >= MAX_CONNECTIONS => {
+                                warn!("Too many connections, closing new connection");
+                            }
+                            Ok(mut stream) => {
+                                let token = Token(next_token);
+                                next_token += 1;
+                                self.poll.registry().register(
+                                    &mut stream,
+                                    token,
+                                    Interest::READABLE,
+                                )?;
+                                let peer = stream.peer();
+                                connections.insert(
+                                    token,
+                                    Connection {
+                                        rate_key: stream.rate_key(peer),
+                                        peer,
+                                        stream,
+                                        read_buf: Vec::new(),
+                                        write_buf: Vec::new(),
+                                        msg_buf: Vec::new(),
+                                        subscribed: false,
+                                    },
+                                );
+                            }
+                            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                            Err(e) => return Err(e.into()),
+                        }
+                    },
+                    WAKER => {
+                        let published = std::mem::take(&mut *self.published.lock());
+                        if published.is_empty() {
+                            continue;
+                        }
+                        let mut dropped = Vec::new();
+                        for (token, conn) in connections.iter_mut().filter(|(_, c)| c.subscribed) {
+                            match conn.push(&published) {
+                                Ok(()) => {
+                                    let interest = conn.interest();
+                                    self.poll.registry().reregister(
+                                        &mut conn.stream,
+                                        *token,
+                                        interest,
+                                    )?;
+                                }
+                                Err(e) => {
+                                    debug!("Closing subscribed connection: {e}");
+                                    dropped.push(*token);
+                                }
+                            }
+                        }
+                        for token in dropped {
+                            if let Some(mut conn) = connections.remove(&token) {
+                                self.poll.registry().deregister(&mut conn.stream)?;
+                            }
+                        }
+                    }
+                    token => {
+                        let Some(conn) = connections.get_mut(&token) else {
+                            continue;
+                        };
+                        let result = conn.read(&f, &mut limiter).and_then(|closed| {
+                            conn.write()?;
+                            Ok(closed)
+                        });
+                        match result {
+                            Ok(false) => {
+                                let interest = conn.interest();
+                                self.poll.registry().reregister(
+                                    &mut conn.stream,
+                                    token,
+                                    interest,
+                                )?;
+                            }
+                            Ok(true) => {
+                                if let Some(mut conn) = connections.remove(&token) {
+                                    self.poll.registry().deregister(&mut conn.stream)?;
+                                }
+                            }
+                            Err(e) => {
+                                debug!("Closing connection: {e}");
+                                if let Some(mut conn) = connections.remove(&token) {
+                                    self.poll.registry().deregister(&mut conn.stream)?;
+                                }
+                            }
+                        }
+                    }
                 }
-                stream.flush()?;
             }
         }
         Ok(())
     }
+}
 
-    pub fn serve<F>(&mut self, f: F) -> Result<()>
-    where
-        F: Fn(&[u8]) -> Option<Vec<u8>>,
-    {
-        loop {
-            self.handle(&f)?;
+impl Drop for SocketServer {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            fs::remove_file(path).unwrap();
         }
     }
+}
 
+/// Socket that answers each datagram with one datagram, without base64 or EOT framing
+///
+/// Clients have to bind their socket to a path to receive the answer.
+pub struct DatagramServer {
+    socket: UnixDatagram,
+    path: PathBuf,
+}
+
+impl DatagramServer {
+    pub fn create(path: PathBuf) -> Result<Self> {
+        if let Some(run_dir) = path.parent() {
+            fs::create_dir_all(run_dir)
+                .with_context(|| format!("Failed to create runtime directory {run_dir:?}"))?;
+        }
+        if path.exists() {
+            warn!("Removing existing socket '{}'", path.display());
+            fs::remove_file(&path).with_context(|| "Failed to remove existing socket")?;
+        }
+        let socket = UnixDatagram::bind(&path)
+            .with_context(|| format!("Failed to bind socket at {path:?}"))?;
+        socket.set_read_timeout(Some(DATAGRAM_POLL))?;
+        info!("Created datagram socket at '{}'", path.display());
+        Ok(Self { socket, path })
+    }
+
+    /// Answers datagrams until the shutdown flag is set
     pub fn serve_until<F>(&mut self, shutdown: Arc<AtomicBool>, f: F) -> Result<()>
     where
         F: Fn(&[u8]) -> Option<Vec<u8>>,
     {
+        let mut buf = vec![0; MAX_DATAGRAM];
         while !shutdown.load(Ordering::Relaxed) {
-            self.handle(&f)?;
+            let (n, addr) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e.into()),
+            };
+            trace!("Received datagram: {:?}", &buf[..n]);
+            let Some(resp) = f(&buf[..n]) else {
+                continue;
+            };
+            match addr.as_pathname() {
+                Some(path) => {
+                    if let Err(e) = self.socket.send_to(&resp, path) {
+                        debug!("Failed to answer datagram: {e}");
+                    }
+                }
+                None => debug!("Can't answer datagram of an unbound socket"),
+            }
         }
         Ok(())
     }
 }
 
-impl Drop for SocketServer {
+impl Drop for DatagramServer {
     fn drop(&mut self) {
-        fs::remove_file(&self.path).unwrap();
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Byte stream from a client to the server, over which the framed messages are exchanged
+///
+/// The server accepts the other end of Unix and TCP streams, [`MemoryTransport`] answers in
+/// the same process instead. There is no named pipe, as only Unix is supported.
+pub trait Transport: Read + Write + Send {
+    /// Signals that no more messages follow, so the server can close the connection
+    fn close_write(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for UnixStream {
+    fn close_write(&mut self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+impl Transport for TcpStream {
+    fn close_write(&mut self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+/// Transport to a handler in the same process, which answers each frame like the server does
+///
+/// Messages are answered as soon as they are written, e.g. to test clients without a socket.
+pub struct MemoryTransport<F> {
+    handler: F,
+    input: Vec<u8>,
+    msg_buf: Vec<u8>,
+    output: Vec<u8>,
+}
+
+impl<F> MemoryTransport<F>
+where
+    F: Fn(&[u8]) -> Option<Vec<u8>> + Send,
+{
+    pub fn new(handler: F) -> Self {
+        Self {
+            handler,
+            input: Vec::new(),
+            msg_buf: Vec::new(),
+            output: Vec::new(),
+        }
     }
 }
 
+impl<F> Read for MemoryTransport<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.output.len());
+        buf[..n].copy_from_slice(&self.output[..n]);
+        self.output.drain(..n);
+        Ok(n)
+    }
+}
+
+impl<F> Write for MemoryTransport<F>
+where
+    F: Fn(&[u8]) -> Option<Vec<u8>>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.input.extend_from_slice(buf);
+        respond_frames(
+            &mut self.input,
+            &mut self.msg_buf,
+            &mut self.output,
+            &self.handler,
+            || true,
+        )
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F> Transport for MemoryTransport<F> where F: Fn(&[u8]) -> Option<Vec<u8>> + Send {}
+
 pub struct SocketClient {
-    stream: UnixStream,
-    reader: BufReader<UnixStream>,
+    /// Only reads are buffered, writes go straight to the transport
+    stream: BufReader<Box<dyn Transport>>,
+    /// Buffers of the last frames, kept to reuse their allocations
+    write_buf: Vec<u8>,
+    read_buf: Vec<u8>,
 }
 
 impl SocketClient {
     pub fn connect(path: PathBuf) -> Result<Self> {
         let stream = UnixStream::connect(&path)
             .with_context(|| format!("Failed to connect to socket {path:?}"))?;
-        let reader = BufReader::new(stream.try_clone()?);
-        Ok(Self { stream, reader })
+        Ok(Self::with_transport(stream))
+    }
+
+    /// Connects to a daemon on the network, e.g. `desktop:7878`
+    pub fn connect_tcp(addr: &str) -> Result<Self> {
+        let stream =
+            TcpStream::connect(addr).with_context(|| format!("Failed to connect to {addr}"))?;
+        Ok(Self::with_transport(stream))
+    }
+
+    pub fn with_transport(transport: impl Transport + 'static) -> Self {
+        Self {
+            stream: BufReader::new(Box::new(transport)),
+            write_buf: Vec::new(),
+            read_buf: Vec::new(),
+        }
     }
 
     pub fn try_send(&mut self, msg: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.queue(msg)?;
+        self.receive()
+    }
+
+    /// Sends a message without waiting for the response, to pipeline several requests
+    pub fn queue(&mut self, msg: &[u8]) -> Result<()> {
         trace!("Sending message over socket: {msg:?}");
-        let encoded = STANDARD_NO_PAD.encode(msg);
-        self.stream.write_all(&[encoded.as_bytes(), &[EOT]].concat())?;
-        self.stream.flush()?;
-        let mut response = Vec::new();
-        self.reader.read_until(EOT, &mut response)?;
-        response.pop();
-        if response.is_empty() {
+        self.write_buf.clear();
+        encode_frame(msg, &mut self.write_buf);
+        let transport = self.stream.get_mut();
+        transport.write_all(&self.write_buf)?;
+        transport.flush()?;
+        Ok(())
+    }
+
+    /// Waits for the response to the oldest queued message, `None` if it is empty
+    pub fn receive(&mut self) -> Result<Option<Vec<u8>>> {
+        self.read_buf.clear();
+        self.stream.read_until(EOT, &mut self.read_buf)?;
+        self.read_buf.pop();
+        if self.read_buf.is_empty() {
             return Ok(None);
         }
-        let decoded = STANDARD_NO_PAD.decode(&response)?;
+        let mut decoded = Vec::new();
+        decode_frame(&self.read_buf, &mut decoded)?;
         trace!("Received response: {decoded:?}");
         Ok(Some(decoded))
     }
@@ -129,6 +788,9 @@ impl SocketClient {
 
 impl Drop for SocketClient {
     fn drop(&mut self) {
-        self.stream.shutdown(Shutdown::Write).unwrap();
+        // The peer may have closed or reset the connection already
+        if let Err(e) = self.stream.get_mut().close_write() {
+            debug!("Failed to close the connection: {e}");
+        }
     }
 }
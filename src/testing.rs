@@ -0,0 +1,116 @@
+//! In-process daemon for integration tests of clients
+use crate::{
+    config::Config,
+    daemon::{Daemon, Simulation},
+    msg::{Encoding, Message, Response},
+    socket::SocketClient,
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::error;
+
+/// Distinguishes the directories of daemons in the same process
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Daemon serving the timers on a socket in a temporary directory
+///
+/// The clock only moves when it is advanced and the time since the last input grows along with
+/// it, nothing is persisted. The daemon stops and removes the directory when it is dropped.
+pub struct TestDaemon {
+    daemon: Daemon,
+    dir: PathBuf,
+    socket: PathBuf,
+}
+
+/// Undo window of [`TestDaemon::start`]
+const UNDO_WINDOW: Duration = Duration::from_secs(30);
+
+impl TestDaemon {
+    pub fn start(config: Config) -> Result<Self> {
+        Self::with_undo_window(config, UNDO_WINDOW)
+    }
+
+    /// Starts the daemon with a different undo window, which is measured in real time
+    pub fn with_undo_window(config: Config, undo_window: Duration) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "{}-test-{}-{}",
+            crate::APP_NAME,
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create '{}'", dir.display()))?;
+        let socket = dir.join(crate::DAEMON_NAME).with_extension("sock");
+        let daemon = Daemon::serve(
+            config,
+            Some(Simulation::new(0.0, VecDeque::new())),
+            false,
+            undo_window,
+            socket.clone(),
+            Arc::new(AtomicBool::new(false)),
+        )?;
+        Ok(Self {
+            daemon,
+            dir,
+            socket,
+        })
+    }
+
+    /// Path of the socket, to point clients at
+    pub fn socket(&self) -> &Path {
+        &self.socket
+    }
+
+    /// Control socket of a read-only daemon
+    pub fn control_socket(&self) -> PathBuf {
+        self.socket.with_extension("control.sock")
+    }
+
+    /// Token that clients of the control socket have to send
+    pub fn control_token(&self) -> Result<String> {
+        let path = self.socket.with_extension("control.token");
+        std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))
+    }
+
+    pub fn connect(&self) -> Result<SocketClient> {
+        SocketClient::connect(self.socket.clone())
+    }
+
+    /// Sends a message over a new connection and waits for the response
+    pub fn send(&self, msg: Message) -> Result<Response> {
+        let response = self.connect()?.send(&msg.encode()?)?;
+        Response::decode(&response)
+    }
+
+    /// Moves the clock forward and updates the timers every second of it, like the daemon does
+    pub fn advance(&self, delta: Duration) -> Result<()> {
+        self.daemon.advance(delta)
+    }
+
+    /// Synchronizes the timers through the sync file of the configuration
+    pub fn sync(&self) -> Result<()> {
+        self.daemon.sync()
+    }
+
+    /// Sets the time since the last input, which then increases with the clock
+    pub fn set_idle(&self, idle: Duration) {
+        self.daemon.set_idle(idle)
+    }
+}
+
+impl Drop for TestDaemon {
+    fn drop(&mut self) {
+        if let Err(e) = self.daemon.stop() {
+            error!("Failed to stop the test daemon: {e}");
+        }
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(not(feature = "x11"))]
+use anyhow::bail;
+#[cfg(feature = "x11")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "x11")]
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{AtomEnum, ConnectionExt, Window},
+    rust_connection::RustConnection,
+};
+
+/// Application of the focused window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusedWindow {
+    /// Class of the application, e.g. `firefox`
+    pub class: String,
+    pub title: String,
+}
+
+impl FocusedWindow {
+    /// Checks whether the application is one of the given classes, ignoring case
+    pub fn is_any(&self, classes: &[String]) -> bool {
+        classes.iter().any(|c| c.eq_ignore_ascii_case(&self.class))
+    }
+}
+
+/// Tracks the focused window through the EWMH properties of the X server
+#[cfg(feature = "x11")]
+pub struct WindowTracker {
+    conn: RustConnection,
+    root: Window,
+    active_window: u32,
+    wm_name: u32,
+    utf8_string: u32,
+}
+
+#[cfg(feature = "x11")]
+impl WindowTracker {
+    pub fn connect() -> Result<Self> {
+        let (conn, screen) = x11rb::connect(None).context("Failed to connect to X server")?;
+        let root = conn.setup().roots[screen].root;
+        let intern =
+            |name: &[u8]| -> Result<u32> { Ok(conn.intern_atom(false, name)?.reply()?.atom) };
+        let active_window = intern(b"_NET_ACTIVE_WINDOW")?;
+        let wm_name = intern(b"_NET_WM_NAME")?;
+        let utf8_string = intern(b"UTF8_STRING")?;
+        Ok(Self {
+            conn,
+            root,
+            active_window,
+            wm_name,
+            utf8_string,
+        })
+    }
+
+    pub fn focused(&mut self) -> Result<Option<FocusedWindow>> {
+        let reply = self
+            .conn
+            .get_property(false, self.root, self.active_window, AtomEnum::WINDOW, 0, 1)?
+            .reply()?;
+        let Some(window) = reply.value32().and_then(|mut v| v.next()) else {
+            return Ok(None);
+        };
+        if window == 0 {
+            return Ok(None);
+        }
+        // WM_CLASS consists of the instance and the class name separated by a null byte
+        let class = self.property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+        let class = class
+            .split(|b| *b == 0)
+            .rfind(|part| !part.is_empty())
+            .unwrap_or_default();
+        let title = self.property(window, self.wm_name, self.utf8_string)?;
+        Ok(Some(FocusedWindow {
+            class: String::from_utf8_lossy(class).into_owned(),
+            title: String::from_utf8_lossy(&title).into_owned(),
+        }))
+    }
+
+    fn property(&self, window: Window, property: u32, kind: u32) -> Result<Vec<u8>> {
+        Ok(self
+            .conn
+            .get_property(false, window, property, kind, 0, u32::MAX)?
+            .reply()?
+            .value)
+    }
+}
+
+/// Stand-in without X11 support, which never connects
+#[cfg(not(feature = "x11"))]
+pub struct WindowTracker;
+
+#[cfg(not(feature = "x11"))]
+impl WindowTracker {
+    pub fn connect() -> Result<Self> {
+        bail!("built without the x11 feature")
+    }
+
+    pub fn focused(&mut self) -> Result<Option<FocusedWindow>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,377 @@
+use movebeam::{
+    config::{Activity, Config, ResetSource, SyncConfig, TimerConfig, TimerType},
+    msg::{Encoding, Message, Response, ResponseError, TimerInfo, TimerSnapshot, TimerStatus},
+    socket::SocketClient,
+    testing::TestDaemon,
+};
+use std::time::Duration;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn timer(name: &str, interval: Duration) -> TimerConfig {
+    TimerConfig {
+        name: name.to_string(),
+        kind: TimerType::Break,
+        interval,
+        ..TimerConfig::default()
+    }
+}
+
+/// Configuration in which the clocks keep running without input
+fn config(timers: Vec<TimerConfig>) -> Config {
+    Config {
+        activity: Some(Activity {
+            inactivity_pause: None,
+            inactivity_reset: None,
+            ..Activity::default()
+        }),
+        timers,
+        ..Config::default()
+    }
+}
+
+fn get(daemon: &TestDaemon, name: &str) -> TimerInfo {
+    match daemon.send(Message::Get(name.to_string())).unwrap() {
+        Response::Timer(info) => info,
+        response => panic!("Unexpected response: {response:?}"),
+    }
+}
+
+fn send_ok(daemon: &TestDaemon, msg: Message) {
+    match daemon.send(msg).unwrap() {
+        Response::Ok => {}
+        response => panic!("Unexpected response: {response:?}"),
+    }
+}
+
+#[test]
+fn clock_follows_the_simulation() {
+    let daemon = TestDaemon::start(config(vec![timer("move", 50 * MINUTE)])).unwrap();
+    assert_eq!(get(&daemon, "move").elapsed, Duration::ZERO);
+    daemon.advance(10 * MINUTE).unwrap();
+    assert_eq!(get(&daemon, "move").elapsed, 10 * MINUTE);
+}
+
+#[test]
+fn reset_and_undo() {
+    let daemon = TestDaemon::start(config(vec![timer("move", 50 * MINUTE)])).unwrap();
+    daemon.advance(10 * MINUTE).unwrap();
+    send_ok(&daemon, Message::Reset("move".to_string()));
+    assert_eq!(get(&daemon, "move").elapsed, Duration::ZERO);
+    send_ok(&daemon, Message::Undo);
+    assert_eq!(get(&daemon, "move").elapsed, 10 * MINUTE);
+    // The undo is used up
+    assert!(matches!(
+        daemon.send(Message::Undo).unwrap(),
+        Response::Error(ResponseError::NothingToUndo)
+    ));
+}
+
+#[test]
+fn undo_after_the_window() {
+    let daemon =
+        TestDaemon::with_undo_window(config(vec![timer("move", 50 * MINUTE)]), Duration::ZERO)
+            .unwrap();
+    daemon.advance(10 * MINUTE).unwrap();
+    send_ok(&daemon, Message::Reset("move".to_string()));
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(matches!(
+        daemon.send(Message::Undo).unwrap(),
+        Response::Error(ResponseError::NothingToUndo)
+    ));
+    assert_eq!(get(&daemon, "move").elapsed, Duration::ZERO);
+}
+
+#[test]
+fn reset_of_nothing_keeps_the_undo() {
+    let daemon = TestDaemon::start(config(vec![
+        timer("move", 50 * MINUTE),
+        TimerConfig {
+            reset_by: ResetSource::Never,
+            ..timer("screen", 8 * 60 * MINUTE)
+        },
+    ]))
+    .unwrap();
+    daemon.advance(10 * MINUTE).unwrap();
+    send_ok(&daemon, Message::Reset("move".to_string()));
+    assert!(matches!(
+        daemon.send(Message::Reset("screen".to_string())).unwrap(),
+        Response::Error(ResponseError::NotResettable)
+    ));
+    send_ok(&daemon, Message::Undo);
+    assert_eq!(get(&daemon, "move").elapsed, 10 * MINUTE);
+    assert_eq!(get(&daemon, "screen").elapsed, 10 * MINUTE);
+}
+
+#[test]
+fn completed_break_resets_the_timer() {
+    let daemon = TestDaemon::start(config(vec![timer("move", 50 * MINUTE)])).unwrap();
+    daemon.advance(20 * MINUTE).unwrap();
+    send_ok(
+        &daemon,
+        Message::Break("move".to_string(), Some(5 * MINUTE)),
+    );
+    // The clock is paused during the break
+    daemon.advance(2 * MINUTE).unwrap();
+    assert_eq!(get(&daemon, "move").elapsed, 20 * MINUTE);
+    daemon.advance(4 * MINUTE).unwrap();
+    assert!(get(&daemon, "move").elapsed < MINUTE);
+}
+
+#[test]
+fn breaks_only_on_break_timers() {
+    let daemon = TestDaemon::start(config(vec![TimerConfig {
+        kind: TimerType::Daily,
+        ..timer("screen", 8 * 60 * MINUTE)
+    }]))
+    .unwrap();
+    assert!(matches!(
+        daemon
+            .send(Message::Break("screen".to_string(), Some(MINUTE)))
+            .unwrap(),
+        Response::Error(ResponseError::NoBreaks)
+    ));
+}
+
+#[test]
+fn names_resolve_by_prefix() {
+    let daemon = TestDaemon::start(config(vec![
+        timer("move", 50 * MINUTE),
+        timer("eyes", 20 * MINUTE),
+        timer("eyes-long", 60 * MINUTE),
+    ]))
+    .unwrap();
+    daemon.advance(MINUTE).unwrap();
+    send_ok(&daemon, Message::Reset("mo".to_string()));
+    assert_eq!(get(&daemon, "move").elapsed, Duration::ZERO);
+    // An exact name wins over the longer names it is a prefix of
+    send_ok(&daemon, Message::Reset("eyes".to_string()));
+    assert_eq!(get(&daemon, "eyes-long").elapsed, MINUTE);
+    match daemon.send(Message::Reset("ey".to_string())).unwrap() {
+        Response::Error(ResponseError::Similar(names)) => {
+            assert_eq!(names, ["eyes", "eyes-long"])
+        }
+        response => panic!("Unexpected response: {response:?}"),
+    }
+    match daemon.send(Message::Reset("mvoe".to_string())).unwrap() {
+        Response::Error(ResponseError::Similar(names)) => assert_eq!(names, ["move"]),
+        response => panic!("Unexpected response: {response:?}"),
+    }
+    assert!(matches!(
+        daemon.send(Message::Reset("stretch".to_string())).unwrap(),
+        Response::Error(ResponseError::NotFound)
+    ));
+}
+
+fn snapshot(name: &str, elapsed: Duration) -> TimerSnapshot {
+    TimerSnapshot {
+        name: name.to_string(),
+        elapsed_secs: elapsed.as_secs(),
+        went_off: false,
+    }
+}
+
+#[test]
+fn import_can_be_undone() {
+    let daemon = TestDaemon::start(config(vec![timer("move", 50 * MINUTE)])).unwrap();
+    daemon.advance(10 * MINUTE).unwrap();
+    send_ok(
+        &daemon,
+        Message::ImportState(vec![snapshot("move", 30 * MINUTE)]),
+    );
+    assert_eq!(get(&daemon, "move").elapsed, 30 * MINUTE);
+    send_ok(&daemon, Message::Undo);
+    assert_eq!(get(&daemon, "move").elapsed, 10 * MINUTE);
+}
+
+#[test]
+fn import_refused_for_timers_that_cant_be_reset() {
+    let daemon = TestDaemon::start(config(vec![
+        timer("move", 50 * MINUTE),
+        TimerConfig {
+            reset_by: ResetSource::Never,
+            ..timer("screen", 8 * 60 * MINUTE)
+        },
+    ]))
+    .unwrap();
+    daemon.advance(10 * MINUTE).unwrap();
+    assert!(matches!(
+        daemon
+            .send(Message::ImportState(vec![
+                snapshot("move", Duration::ZERO),
+                snapshot("screen", Duration::ZERO),
+            ]))
+            .unwrap(),
+        Response::Error(ResponseError::NotResettable)
+    ));
+    // Nothing is imported when one of the timers can't be reset
+    assert_eq!(get(&daemon, "move").elapsed, 10 * MINUTE);
+    assert_eq!(get(&daemon, "screen").elapsed, 10 * MINUTE);
+}
+
+/// Configuration that resets the timers after five minutes without input and synchronizes them
+/// through the file
+fn synced_config(timers: Vec<TimerConfig>, file: &str) -> Config {
+    Config {
+        activity: Some(Activity {
+            inactivity_pause: None,
+            inactivity_reset: Some(5 * MINUTE),
+            ..Activity::default()
+        }),
+        sync: Some(SyncConfig {
+            file: std::env::temp_dir()
+                .join(format!("movebeam-test-{}-{file}.toml", std::process::id())),
+            interval: None,
+        }),
+        timers,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn sync_keeps_an_inactivity_reset() {
+    let config = synced_config(vec![timer("move", 50 * MINUTE)], "inactivity");
+    let file = config.sync.as_ref().unwrap().file.clone();
+    let daemon = TestDaemon::start(config).unwrap();
+    daemon.advance(4 * MINUTE).unwrap();
+    daemon.sync().unwrap();
+    // The time without input reaches the inactivity reset
+    daemon.advance(2 * MINUTE).unwrap();
+    assert_eq!(get(&daemon, "move").elapsed, Duration::ZERO);
+    daemon.sync().unwrap();
+    assert_eq!(get(&daemon, "move").elapsed, Duration::ZERO);
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn sync_doesnt_reset_timers_that_never_reset() {
+    let timers = vec![TimerConfig {
+        reset_by: ResetSource::Never,
+        ..timer("screen", 8 * 60 * MINUTE)
+    }];
+    let config = synced_config(timers, "never");
+    let file = config.sync.as_ref().unwrap().file.clone();
+    let daemon = TestDaemon::start(config).unwrap();
+    daemon.advance(4 * MINUTE).unwrap();
+    daemon.sync().unwrap();
+    // The other machine has a newer reset, which this timer ignores
+    std::fs::write(
+        &file,
+        "last_input = 0\n[timers.screen]\nelapsed_secs = 0\nreset = 4000000000\n",
+    )
+    .unwrap();
+    daemon.sync().unwrap();
+    assert_eq!(get(&daemon, "screen").elapsed, 4 * MINUTE);
+    std::fs::remove_file(file).unwrap();
+}
+
+/// Sends a message to the control socket of the daemon
+fn send_control(daemon: &TestDaemon, msg: Message) -> Response {
+    let mut client = SocketClient::connect(daemon.control_socket()).unwrap();
+    Response::decode(&client.send(&msg.encode().unwrap()).unwrap()).unwrap()
+}
+
+#[test]
+fn control_socket_requires_the_token() {
+    let daemon = TestDaemon::start(Config {
+        read_only: true,
+        ..config(vec![timer("move", 50 * MINUTE)])
+    })
+    .unwrap();
+    daemon.advance(10 * MINUTE).unwrap();
+    let reset = || Message::Reset("move".to_string());
+    assert!(matches!(
+        daemon.send(reset()).unwrap(),
+        Response::Error(ResponseError::ReadOnly)
+    ));
+    assert!(matches!(
+        send_control(&daemon, reset()),
+        Response::Error(ResponseError::ReadOnly)
+    ));
+    assert!(matches!(
+        send_control(
+            &daemon,
+            Message::Authenticated("guess".to_string(), Box::new(reset()))
+        ),
+        Response::Error(ResponseError::ReadOnly)
+    ));
+    // Queries don't need the token
+    assert!(matches!(
+        send_control(&daemon, Message::Get("move".to_string())),
+        Response::Timer(_)
+    ));
+    assert_eq!(get(&daemon, "move").elapsed, 10 * MINUTE);
+    let token = daemon.control_token().unwrap();
+    assert!(matches!(
+        send_control(&daemon, Message::Authenticated(token, Box::new(reset()))),
+        Response::Ok
+    ));
+    assert_eq!(get(&daemon, "move").elapsed, Duration::ZERO);
+}
+
+#[test]
+fn status_follows_the_clock() {
+    let daemon = TestDaemon::start(Config {
+        activity: Some(Activity {
+            inactivity_pause: Some(2 * MINUTE),
+            inactivity_reset: None,
+            ..Activity::default()
+        }),
+        timers: vec![timer("move", 3 * MINUTE)],
+        ..Config::default()
+    })
+    .unwrap();
+    daemon.advance(MINUTE).unwrap();
+    assert_eq!(get(&daemon, "move").status, TimerStatus::Running);
+    // The clock stops once the time without input passes the inactivity pause
+    daemon.advance(2 * MINUTE).unwrap();
+    let info = get(&daemon, "move");
+    assert_eq!(info.status, TimerStatus::PausedIdle);
+    assert_eq!(info.elapsed, 2 * MINUTE);
+    daemon.set_idle(Duration::ZERO);
+    daemon.advance(MINUTE / 2).unwrap();
+    assert_eq!(get(&daemon, "move").status, TimerStatus::Running);
+    daemon.advance(MINUTE).unwrap();
+    assert_eq!(get(&daemon, "move").status, TimerStatus::Fired);
+    send_ok(
+        &daemon,
+        Message::Break("move".to_string(), Some(5 * MINUTE)),
+    );
+    assert_eq!(get(&daemon, "move").status, TimerStatus::PausedBreak);
+}
+
+/// Receives the changes that the daemon pushes to a subscriber until one matches
+fn changed(client: &mut SocketClient, name: &str, matches: impl Fn(&TimerInfo) -> bool) {
+    loop {
+        let frame = client.receive().unwrap().expect("connection closed");
+        match Response::decode(&frame).unwrap() {
+            Response::Changed(changed) => {
+                if changed
+                    .iter()
+                    .any(|(timer, info)| timer == name && matches(info))
+                {
+                    return;
+                }
+            }
+            response => panic!("Unexpected response: {response:?}"),
+        }
+    }
+}
+
+#[test]
+fn subscribers_are_told_about_changes() {
+    let daemon = TestDaemon::start(config(vec![
+        timer("move", 50 * MINUTE),
+        timer("eyes", 20 * MINUTE),
+    ]))
+    .unwrap();
+    let mut client = daemon.connect().unwrap();
+    let response = client.send(&Message::Subscribe.encode().unwrap()).unwrap();
+    assert!(matches!(Response::decode(&response).unwrap(), Response::Ok));
+    daemon.advance(10 * MINUTE).unwrap();
+    send_ok(&daemon, Message::Reset("move".to_string()));
+    changed(&mut client, "move", |info| info.elapsed.is_zero());
+    // Going off is pushed as well, without a message to the daemon
+    daemon.advance(11 * MINUTE).unwrap();
+    changed(&mut client, "eyes", |info| info.elapsed >= info.interval);
+}